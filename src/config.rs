@@ -1,25 +1,221 @@
+use crate::utils::error::IndexerError;
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub rpc_endpoint: String,
+    /// Additional providers used as hedge targets for the primary endpoint.
+    pub secondary_rpc_endpoints: Vec<String>,
     pub blocks_in_memory: usize,
     pub metrics_port: u16,
     pub data_dir: PathBuf,
+    /// Blocks per output file; files are named after the aligned window of
+    /// blocks they belong to, with a `_<part>` suffix for a window written in
+    /// more than one go.
     pub rotation_blocks: u64,
+    /// Longest a buffered block waits before being written; 0 waits for a
+    /// full `blocks_in_memory` batch.
+    pub flush_interval_secs: u64,
+    /// Estimated size at which buffered blocks are written; 0 disables.
+    pub flush_max_bytes: usize,
+    /// Close open files after this long without new blocks so their data
+    /// becomes readable; unset keeps them open until the window ends.
+    pub finalize_idle_secs: Option<u64>,
+    /// Physical types of hash, address, wei and block time columns.
+    pub column_types: ColumnTypes,
+    /// First block to index. Unset continues after the highest block
+    /// already in `data_dir`, or at the head if it is empty.
     pub start_block: Option<u64>,
+    pub index_uncles: bool,
+    /// Fetch receipts for every block and write their logs.
+    pub index_logs: bool,
+    /// Decode ERC-20/721/1155 transfers into `token_transfers` files.
+    pub index_token_transfers: bool,
+    /// Read metadata of tokens seen in transfers into `tokens` files.
+    pub index_tokens: bool,
+    /// Record deployed contracts and their code into `contracts` files.
+    pub index_contracts: bool,
+    /// Find contracts created by internal calls with `trace_block`, which
+    /// the node must support.
+    pub trace_contract_creations: bool,
+    /// Record per-transaction account changes into `state_diffs` files.
+    pub index_state_diffs: bool,
+    pub state_diff_tracer: StateDiffTracer,
+    pub filter: FilterConfig,
+    /// Directory of contract ABI JSON files to decode events and calls with.
+    pub abi_dir: Option<PathBuf>,
+    /// Extra text signatures naming selectors and topics, on top of the
+    /// bundled ones.
+    pub signatures_path: Option<PathBuf>,
+    /// WebSocket endpoint to record pending transactions from; unset
+    /// disables mempool capture.
+    pub mempool_ws_endpoint: Option<String>,
+    pub rate_limit: RateLimitConfig,
+    pub hedge: HedgeConfig,
+    pub rpc_mode: RpcMode,
+    /// Gzip-compressed JSONL file holding recorded RPC exchanges.
+    pub cassette_path: PathBuf,
+    /// Where blocks come from. Only the `rpc` source is checked against the
+    /// chain recorded in `data_dir`; dump files carry no chain identity.
+    pub block_source: BlockSource,
+    /// JSON, JSONL or gzipped block dump, or a directory of them, read in
+    /// `files` mode.
+    pub source_path: Option<PathBuf>,
+    pub malformed_block_policy: MalformedBlockPolicy,
+    pub head_poll: HeadPollConfig,
+    /// Refetch blocks missing below the head in a background worker.
+    pub backfill_gaps: bool,
+    /// Workers indexing history from `start_block` in parallel while the
+    /// head is followed separately; 0 indexes everything in one pass. Holes
+    /// found below a resume point get one worker even when this is 0.
+    pub backfill_workers: usize,
+    /// Size of the block ranges handed to backfill workers.
+    pub backfill_range_blocks: u64,
+    /// Seconds allowed after SIGTERM/SIGINT for buffered blocks to be
+    /// written and files closed before the process gives up.
+    pub shutdown_timeout_secs: u64,
 }
 
-impl Config {
-    pub fn load() -> Result<Self> {
-        Self::from_env()
+/// How hashes, addresses, wei amounts and block times are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnTypes {
+    /// Hex and decimal strings and `UInt64` seconds, as in earlier files.
+    Strings,
+    /// `FixedSizeBinary`, `Decimal256(76, 0)` and `Timestamp(Second, UTC)`.
+    Native,
+}
+
+impl std::str::FromStr for ColumnTypes {
+    type Err = IndexerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strings" => Ok(Self::Strings),
+            "native" => Ok(Self::Native),
+            other => Err(IndexerError::ConfigError(format!("Unknown COLUMN_TYPES: {}", other))),
+        }
     }
+}
+
+/// What to do with a block that cannot be decoded, after it has been written
+/// to the dead-letter file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MalformedBlockPolicy {
+    /// Refetch until the node returns a valid block, e.g. once it is no
+    /// longer pending. Dump files cannot change, so there it acts as `Skip`.
+    Retry,
+    /// Move on and leave a gap at the block.
+    Skip,
+    /// Stop the processor.
+    Abort,
+}
+
+impl std::str::FromStr for MalformedBlockPolicy {
+    type Err = IndexerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "retry" => Ok(Self::Retry),
+            "skip" => Ok(Self::Skip),
+            "abort" => Ok(Self::Abort),
+            other => Err(IndexerError::ConfigError(format!("Unknown MALFORMED_BLOCK_POLICY: {}", other))),
+        }
+    }
+}
+
+/// Node API used to replay a block's transactions for state diffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateDiffTracer {
+    /// `trace_replayBlockTransactions` with `stateDiff` (Erigon, Nethermind, Reth).
+    Trace,
+    /// `debug_traceBlockByNumber` with the `prestateTracer` in diff mode (Geth).
+    Prestate,
+}
+
+impl std::str::FromStr for StateDiffTracer {
+    type Err = IndexerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "prestate" => Ok(Self::Prestate),
+            other => Err(IndexerError::ConfigError(format!("Unknown STATE_DIFF_TRACER: {}", other))),
+        }
+    }
+}
+
+/// Where `BlockProcessor` gets blocks from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSource {
+    Rpc,
+    /// Local `eth_getBlockByNumber` responses or blocks, one per line, pretty
+    /// printed or in a JSON array, optionally gzipped.
+    Files,
+}
+
+impl std::str::FromStr for BlockSource {
+    type Err = IndexerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rpc" => Ok(Self::Rpc),
+            "files" => Ok(Self::Files),
+            other => Err(IndexerError::ConfigError(format!("Unknown BLOCK_SOURCE: {}", other))),
+        }
+    }
+}
+
+/// Whether RPC traffic goes to the network, is also recorded to the
+/// cassette, or is served entirely from the cassette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcMode {
+    Live,
+    Record,
+    Replay,
+}
+
+impl std::str::FromStr for RpcMode {
+    type Err = IndexerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "live" => Ok(Self::Live),
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            other => Err(IndexerError::ConfigError(format!("Unknown RPC_MODE: {}", other))),
+        }
+    }
+}
 
+impl Config {
     pub fn from_env() -> Result<Self> {
+        let data_dir = PathBuf::from(std::env::var("DATA_DIR")
+            .unwrap_or_else(|_| "/data/eth-indexer".to_string()));
+        let cassette_path = std::env::var("RPC_CASSETTE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| data_dir.join("rpc_cassette.jsonl.gz"));
+
+        let block_source = std::env::var("BLOCK_SOURCE")
+            .map(|v| v.parse())
+            .unwrap_or(Ok(BlockSource::Rpc))?;
+        let source_path = std::env::var("SOURCE_PATH").ok().map(PathBuf::from);
+        if block_source == BlockSource::Files && source_path.is_none() {
+            return Err(IndexerError::ConfigError("SOURCE_PATH is required when BLOCK_SOURCE=files".into()).into());
+        }
+
         Ok(Self {
             rpc_endpoint: std::env::var("RPC_ENDPOINT")
                 .unwrap_or_else(|_| "https://rpc.sepolia.org".to_string()),
+            secondary_rpc_endpoints: std::env::var("SECONDARY_RPC_ENDPOINTS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|endpoint| endpoint.trim().to_string())
+                        .filter(|endpoint| !endpoint.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
             blocks_in_memory: std::env::var("BLOCKS_IN_MEMORY")
                 .ok()
                 .and_then(|v| v.parse().ok())
@@ -28,12 +224,26 @@ impl Config {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(9090),
-            data_dir: PathBuf::from(std::env::var("DATA_DIR")
-                .unwrap_or_else(|_| "/data/eth-indexer".to_string())),
+            data_dir,
             rotation_blocks: std::env::var("ROTATION_BLOCKS")
                 .ok()
                 .and_then(|v| v.parse().ok())
+                .filter(|&blocks: &u64| blocks > 0)
                 .unwrap_or(10000),
+            flush_interval_secs: std::env::var("FLUSH_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            flush_max_bytes: std::env::var("FLUSH_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(64 * 1024 * 1024),
+            finalize_idle_secs: std::env::var("FINALIZE_IDLE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            column_types: std::env::var("COLUMN_TYPES")
+                .map(|v| v.parse())
+                .unwrap_or(Ok(ColumnTypes::Strings))?,
             start_block: std::env::var("START_BLOCK")
                 .ok()
                 .and_then(|v| {
@@ -43,6 +253,253 @@ impl Config {
                         v.parse::<f64>().ok().map(|f| f as u64)
                     })
                 }),
+            index_uncles: std::env::var("INDEX_UNCLES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            index_logs: std::env::var("INDEX_LOGS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            index_token_transfers: std::env::var("INDEX_TOKEN_TRANSFERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            index_tokens: std::env::var("INDEX_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            index_contracts: std::env::var("INDEX_CONTRACTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            trace_contract_creations: std::env::var("TRACE_CONTRACT_CREATIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            index_state_diffs: std::env::var("INDEX_STATE_DIFFS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            state_diff_tracer: std::env::var("STATE_DIFF_TRACER")
+                .map(|v| v.parse())
+                .unwrap_or(Ok(StateDiffTracer::Trace))?,
+            filter: FilterConfig::from_env(),
+            abi_dir: std::env::var("ABI_DIR").ok().map(PathBuf::from),
+            signatures_path: std::env::var("SIGNATURES_PATH").ok().map(PathBuf::from),
+            mempool_ws_endpoint: std::env::var("MEMPOOL_WS_ENDPOINT").ok(),
+            rate_limit: RateLimitConfig::from_env()?,
+            hedge: HedgeConfig::from_env(),
+            rpc_mode: std::env::var("RPC_MODE")
+                .map(|v| v.parse())
+                .unwrap_or(Ok(RpcMode::Live))?,
+            cassette_path,
+            block_source,
+            source_path,
+            malformed_block_policy: std::env::var("MALFORMED_BLOCK_POLICY")
+                .map(|v| v.parse())
+                .unwrap_or(Ok(MalformedBlockPolicy::Retry))?,
+            head_poll: HeadPollConfig::from_env(),
+            backfill_gaps: std::env::var("BACKFILL_GAPS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+            backfill_workers: std::env::var("BACKFILL_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            backfill_range_blocks: std::env::var("BACKFILL_RANGE_BLOCKS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&blocks: &u64| blocks > 0)
+                .unwrap_or(10000),
+            shutdown_timeout_secs: std::env::var("SHUTDOWN_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
         })
     }
-}
\ No newline at end of file
+}
+
+/// Client-side request budget applied to each RPC endpoint.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub requests_per_second: Option<f64>,
+    pub compute_units_per_second: Option<f64>,
+    /// Compute-unit weight per JSON-RPC method, e.g.
+    /// `eth_getBlockByNumber=16,eth_getUncleByBlockNumberAndIndex=16`.
+    pub method_compute_units: HashMap<String, u64>,
+    pub default_compute_units: u64,
+    pub daily_compute_units: Option<u64>,
+    pub monthly_compute_units: Option<u64>,
+    /// Percentage of the daily and monthly budgets kept for following the
+    /// head; backfills pause once the rest is spent.
+    pub head_reserve_percent: u64,
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Result<Self> {
+        let config = Self {
+            requests_per_second: std::env::var("RPC_REQUESTS_PER_SECOND")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            compute_units_per_second: std::env::var("RPC_COMPUTE_UNITS_PER_SECOND")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            method_compute_units: std::env::var("RPC_METHOD_COMPUTE_UNITS")
+                .map(|v| {
+                    v.split(',')
+                        .filter_map(|entry| {
+                            let (method, units) = entry.split_once('=')?;
+                            Some((method.trim().to_string(), units.trim().parse().ok()?))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            default_compute_units: std::env::var("RPC_DEFAULT_COMPUTE_UNITS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1),
+            daily_compute_units: std::env::var("RPC_DAILY_COMPUTE_UNITS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            monthly_compute_units: std::env::var("RPC_MONTHLY_COMPUTE_UNITS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            head_reserve_percent: std::env::var("RPC_BUDGET_HEAD_RESERVE_PERCENT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&percent: &u64| percent < 100)
+                .unwrap_or(10),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Compute units of a budget `limit` that backfill requests may spend.
+    pub fn backfill_share(&self, limit: u64) -> u64 {
+        (u128::from(limit) * u128::from(100 - self.head_reserve_percent) / 100) as u64
+    }
+
+    /// Rejects methods that cost more than a budget allows backfills in a
+    /// whole day or month, as requests for them would wait forever.
+    fn validate(&self) -> Result<()> {
+        let costs = self.method_compute_units.iter()
+            .map(|(method, &units)| (method.as_str(), units))
+            .chain(std::iter::once(("the default", self.default_compute_units)));
+        for (method, units) in costs {
+            for (period, limit) in [("daily", self.daily_compute_units), ("monthly", self.monthly_compute_units)] {
+                if let Some(share) = limit.map(|limit| self.backfill_share(limit)).filter(|&share| units > share) {
+                    return Err(IndexerError::ConfigError(format!(
+                        "Compute units of {} ({}) exceed the {} backfill budget of {}",
+                        method, units, period, share
+                    )).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hedging of slow requests to the primary endpoint onto a secondary one.
+#[derive(Debug, Clone)]
+pub struct HedgeConfig {
+    pub enabled: bool,
+    /// Latency percentile of the primary endpoint after which a hedge is sent.
+    pub percentile: f64,
+    pub min_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl HedgeConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("RPC_HEDGE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            percentile: std::env::var("RPC_HEDGE_PERCENTILE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.95),
+            min_delay_ms: std::env::var("RPC_HEDGE_MIN_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(50),
+            max_delay_ms: std::env::var("RPC_HEDGE_MAX_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2000),
+        }
+    }
+}
+
+/// Bounds for the adaptive `eth_blockNumber` poll once the processor has
+/// caught up with the head.
+#[derive(Debug, Clone)]
+pub struct HeadPollConfig {
+    pub min_interval_ms: u64,
+    pub max_interval_ms: u64,
+}
+
+impl HeadPollConfig {
+    pub fn from_env() -> Self {
+        Self {
+            min_interval_ms: std::env::var("HEAD_POLL_MIN_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100),
+            max_interval_ms: std::env::var("HEAD_POLL_MAX_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(15000),
+        }
+    }
+}
+
+/// Restricts stored transactions, logs and authorizations to those matching
+/// any of the criteria. With no criteria set everything is stored.
+#[derive(Debug, Clone, Default)]
+pub struct FilterConfig {
+    /// Addresses matched against transaction `from` and `to`.
+    pub addresses: HashSet<String>,
+    pub contract_creations: bool,
+    /// 4-byte function selectors matched against transaction input.
+    pub selectors: HashSet<String>,
+    /// Contracts whose logs, and the transactions emitting them, are kept.
+    pub log_emitters: HashSet<String>,
+    /// Skip the receipt fetch for blocks whose logs bloom rules out a match.
+    pub use_bloom: bool,
+}
+
+impl FilterConfig {
+    pub fn from_env() -> Self {
+        Self {
+            addresses: hex_set_from_env("FILTER_ADDRESSES"),
+            contract_creations: std::env::var("FILTER_CONTRACT_CREATIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+            selectors: hex_set_from_env("FILTER_SELECTORS"),
+            log_emitters: hex_set_from_env("FILTER_LOG_EMITTERS"),
+            use_bloom: std::env::var("FILTER_USE_BLOOM")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Comma-separated hex values, lowercased and 0x-prefixed to match how
+/// addresses and selectors are formatted in the models.
+fn hex_set_from_env(name: &str) -> HashSet<String> {
+    std::env::var(name)
+        .map(|v| {
+            v.split(',')
+                .map(|value| value.trim().trim_start_matches("0x").to_ascii_lowercase())
+                .filter(|value| !value.is_empty())
+                .map(|value| format!("0x{}", value))
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,700 @@
+use crate::config::ColumnTypes;
+use crate::core::datasets::ColumnKind;
+use crate::core::filter::bloom_contains;
+use crate::models::{Block, DecodedRow, DecodedValue};
+use crate::utils::error::IndexerError;
+use anyhow::Result;
+use arrow::datatypes::{DataType, Field, Schema};
+use metrics::counter;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
+use tracing::{info, warn};
+use web3::{
+    ethabi::{Contract, Event, Function, ParamType, RawLog, Token},
+    types::{H256, U256},
+};
+
+/// Whether a table holds decoded events or decoded calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Event,
+    Call,
+}
+
+/// A per-contract, per-event or per-function table derived from an ABI.
+#[derive(Debug, Clone)]
+pub struct AbiTable {
+    pub name: String,
+    pub kind: TableKind,
+    pub schema: Arc<Schema>,
+}
+
+struct EventDecoder {
+    table: String,
+    /// Event with parameters renamed to their column names, which are
+    /// unique; ethabi matches decoded values to parameters by name.
+    event: Event,
+    columns: Vec<DataType>,
+}
+
+struct FunctionDecoder {
+    table: String,
+    function: Function,
+    columns: Vec<DataType>,
+}
+
+struct ContractAbi {
+    events: HashMap<H256, EventDecoder>,
+    functions: HashMap<[u8; 4], FunctionDecoder>,
+}
+
+/// ABIs loaded from `ABI_DIR`, keyed by contract address.
+///
+/// Each file is either a plain ABI array named `<name>_<address>.json` or
+/// `<address>.json`, or an object `{"name": .., "address": .. | [..], "abi": [..]}`.
+#[derive(Default)]
+pub struct AbiRegistry {
+    contracts: HashMap<String, Arc<ContractAbi>>,
+    tables: Vec<AbiTable>,
+    /// Raw bytes of every contract address, for bloom lookups.
+    address_bytes: Vec<Vec<u8>>,
+}
+
+impl AbiRegistry {
+    pub fn load(dir: Option<&Path>, types: ColumnTypes) -> Result<Self> {
+        let mut registry = Self::default();
+        let Some(dir) = dir else {
+            return Ok(registry);
+        };
+
+        let mut files: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .collect();
+        files.sort();
+
+        let mut table_names = HashSet::new();
+        for path in files {
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+            let json: Value = serde_json::from_slice(&std::fs::read(&path)?)
+                .map_err(|e| IndexerError::ConfigError(format!("{}: {}", path.display(), e)))?;
+
+            let (name, addresses, abi) = match json {
+                Value::Object(mut object) => {
+                    let name = object.get("name").and_then(Value::as_str).map(str::to_string);
+                    let addresses = match object.get("address") {
+                        Some(Value::String(address)) => vec![address.clone()],
+                        Some(Value::Array(list)) => list.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+                        _ => Vec::new(),
+                    };
+                    let abi = object.remove("abi").unwrap_or(Value::Null);
+                    (name.unwrap_or_else(|| stem.clone()), addresses, abi)
+                }
+                abi => match stem.rsplit_once("0x") {
+                    Some((prefix, address)) => {
+                        let name = prefix.trim_end_matches('_');
+                        let name = if name.is_empty() { format!("0x{}", address) } else { name.to_string() };
+                        (name, vec![format!("0x{}", address)], abi)
+                    }
+                    None => (stem.clone(), Vec::new(), abi),
+                },
+            };
+
+            if addresses.is_empty() {
+                warn!(
+                    event = "abi_without_address",
+                    message = "Skipping ABI file with no contract address",
+                    path = %path.display()
+                );
+                continue;
+            }
+
+            let contract = Contract::load(serde_json::to_vec(&abi)?.as_slice())
+                .map_err(|e| IndexerError::ConfigError(format!("{}: {}", path.display(), e)))?;
+            let (contract, tables) = build_contract(&sanitize(&name), &contract, types);
+
+            for table in &tables {
+                if !table_names.insert(table.name.clone()) {
+                    return Err(IndexerError::ConfigError(format!(
+                        "{}: table {} is already defined by another ABI file",
+                        path.display(), table.name
+                    )).into());
+                }
+            }
+            registry.tables.extend(tables);
+
+            let contract = Arc::new(contract);
+            for address in addresses {
+                let address = address.trim().to_ascii_lowercase();
+                if let Ok(bytes) = hex::decode(address.trim_start_matches("0x")) {
+                    registry.address_bytes.push(bytes);
+                }
+                registry.contracts.insert(address, contract.clone());
+            }
+        }
+
+        info!(
+            event = "abis_loaded",
+            message = "Loaded contract ABIs",
+            path = %dir.display(),
+            contracts = registry.contracts.len(),
+            tables = registry.tables.len()
+        );
+
+        Ok(registry)
+    }
+
+    pub fn tables(&self) -> &[AbiTable] {
+        &self.tables
+    }
+
+    /// Whether receipts are needed to decode events in `block`.
+    pub fn needs_receipts(&self, block: &Block, bloom: Option<&[u8]>) -> bool {
+        if block.transactions.is_empty() || !self.contracts.values().any(|contract| !contract.events.is_empty()) {
+            return false;
+        }
+        match bloom {
+            Some(bloom) => self.address_bytes.iter().any(|address| bloom_contains(bloom, address)),
+            None => true,
+        }
+    }
+
+    /// Decodes the block's logs and transactions that involve a known
+    /// contract. Data that does not match the ABI is counted, not returned.
+    pub fn decode(&self, block: &Block) -> Vec<DecodedRow> {
+        let mut rows = Vec::new();
+        if self.contracts.is_empty() {
+            return rows;
+        }
+
+        for log in &block.logs {
+            let Some(contract) = self.contracts.get(&log.address) else {
+                continue;
+            };
+            let Some(decoder) = log.topics.first()
+                .and_then(|topic| parse_topic(topic))
+                .and_then(|topic0| contract.events.get(&topic0))
+            else {
+                counter!("abi_unmatched_total", "kind" => "event").increment(1);
+                continue;
+            };
+
+            let raw = RawLog {
+                topics: log.topics.iter().filter_map(|topic| parse_topic(topic)).collect(),
+                data: hex::decode(log.data.trim_start_matches("0x")).unwrap_or_default(),
+            };
+            match decoder.event.parse_log(raw) {
+                Ok(parsed) => {
+                    counter!("abi_decoded_total", "kind" => "event").increment(1);
+                    rows.push(DecodedRow {
+                        table: decoder.table.clone(),
+                        block_number: log.block_number,
+                        transaction_hash: log.transaction_hash.clone(),
+                        log_index: Some(log.log_index),
+                        address: log.address.clone(),
+                        from: None,
+                        value: None,
+                        values: parsed.params.iter()
+                            .zip(&decoder.columns)
+                            .map(|(param, column)| to_value(&param.value, column))
+                            .collect(),
+                    });
+                }
+                Err(_) => counter!("abi_undecodable_total", "kind" => "event").increment(1),
+            }
+        }
+
+        for tx in &block.transactions {
+            let Some(contract) = tx.to.as_ref().and_then(|to| self.contracts.get(to)) else {
+                continue;
+            };
+            let Ok(input) = hex::decode(tx.input.trim_start_matches("0x")) else {
+                counter!("abi_undecodable_total", "kind" => "call").increment(1);
+                continue;
+            };
+            // Plain transfers hit receive/fallback and carry nothing to decode.
+            let Some(selector) = input.get(..4).and_then(|selector| <[u8; 4]>::try_from(selector).ok()) else {
+                continue;
+            };
+            let Some(decoder) = contract.functions.get(&selector) else {
+                counter!("abi_unmatched_total", "kind" => "call").increment(1);
+                continue;
+            };
+
+            match decoder.function.decode_input(&input[4..]) {
+                Ok(tokens) => {
+                    counter!("abi_decoded_total", "kind" => "call").increment(1);
+                    rows.push(DecodedRow {
+                        table: decoder.table.clone(),
+                        block_number: block.number,
+                        transaction_hash: tx.hash.clone(),
+                        log_index: None,
+                        address: tx.to.clone().unwrap_or_default(),
+                        from: Some(tx.from.clone()),
+                        value: Some(tx.value.clone()),
+                        values: tokens.iter()
+                            .zip(&decoder.columns)
+                            .map(|(token, column)| to_value(token, column))
+                            .collect(),
+                    });
+                }
+                Err(_) => counter!("abi_undecodable_total", "kind" => "call").increment(1),
+            }
+        }
+
+        rows
+    }
+}
+
+fn parse_topic(topic: &str) -> Option<H256> {
+    let bytes = hex::decode(topic.trim_start_matches("0x")).ok()?;
+    (bytes.len() == 32).then(|| H256::from_slice(&bytes))
+}
+
+fn build_contract(name: &str, contract: &Contract, types: ColumnTypes) -> (ContractAbi, Vec<AbiTable>) {
+    let mut events = HashMap::new();
+    let mut functions = HashMap::new();
+    let mut tables = Vec::new();
+
+    for overloads in contract.events.values() {
+        for event in overloads.iter().filter(|event| !event.anonymous) {
+            let signature = event.signature();
+            let table = table_name(name, "", &event.name, overloads.len(), &signature.as_bytes()[..4]);
+
+            let mut event = event.clone();
+            let names = column_names(event.inputs.iter().map(|input| input.name.as_str()), EVENT_COLUMNS);
+            let mut columns = Vec::with_capacity(event.inputs.len());
+            for (input, name) in event.inputs.iter_mut().zip(names) {
+                input.name = name;
+                // Indexed dynamic values only survive as their keccak hash.
+                let hashed = input.indexed && matches!(
+                    input.kind,
+                    ParamType::String | ParamType::Bytes | ParamType::Array(_) | ParamType::FixedArray(..) | ParamType::Tuple(_)
+                );
+                columns.push(if hashed { DataType::Utf8 } else { column_type(&input.kind) });
+            }
+
+            tables.push(AbiTable {
+                name: table.clone(),
+                kind: TableKind::Event,
+                schema: table_schema(event_fields(types), event.inputs.iter().map(|input| &input.name), &columns),
+            });
+            events.insert(signature, EventDecoder { table, event, columns });
+        }
+    }
+
+    for overloads in contract.functions.values() {
+        for function in overloads {
+            let selector = function.short_signature();
+            let table = table_name(name, "call_", &function.name, overloads.len(), &selector);
+            let names = column_names(function.inputs.iter().map(|input| input.name.as_str()), CALL_COLUMNS);
+            let columns: Vec<DataType> = function.inputs.iter().map(|input| column_type(&input.kind)).collect();
+
+            tables.push(AbiTable {
+                name: table.clone(),
+                kind: TableKind::Call,
+                schema: table_schema(call_fields(types), names.iter(), &columns),
+            });
+            functions.insert(selector, FunctionDecoder { table, function: function.clone(), columns });
+        }
+    }
+
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    (ContractAbi { events, functions }, tables)
+}
+
+const EVENT_COLUMNS: &[&str] = &["block_number", "transaction_hash", "log_index", "address"];
+const CALL_COLUMNS: &[&str] = &["block_number", "transaction_hash", "address", "from", "value"];
+
+fn event_fields(types: ColumnTypes) -> Vec<Field> {
+    vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("transaction_hash", ColumnKind::Hash.data_type(types), false),
+        Field::new("log_index", DataType::UInt32, false),
+        Field::new("address", ColumnKind::Address.data_type(types), false),
+    ]
+}
+
+fn call_fields(types: ColumnTypes) -> Vec<Field> {
+    vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("transaction_hash", ColumnKind::Hash.data_type(types), false),
+        Field::new("address", ColumnKind::Address.data_type(types), false),
+        Field::new("from", ColumnKind::Address.data_type(types), false),
+        Field::new("value", ColumnKind::Wei.data_type(types), false),
+    ]
+}
+
+fn table_schema<'a>(
+    mut fields: Vec<Field>,
+    names: impl Iterator<Item = &'a String>,
+    columns: &[DataType],
+) -> Arc<Schema> {
+    fields.extend(names.zip(columns).map(|(name, column)| Field::new(name, column.clone(), true)));
+    Arc::new(Schema::new(fields))
+}
+
+/// `<contract>_<item>`, with the selector appended when the name is overloaded.
+fn table_name(contract: &str, prefix: &str, item: &str, overloads: usize, selector: &[u8]) -> String {
+    let mut name = format!("{}_{}{}", contract, prefix, sanitize(item));
+    if overloads > 1 {
+        name.push('_');
+        name.push_str(&hex::encode(selector));
+    }
+    name
+}
+
+/// Column names for ABI parameters: unnamed ones become `argN`, and names
+/// clashing with the fixed columns or each other get an `arg_` prefix.
+fn column_names<'a>(names: impl Iterator<Item = &'a str>, reserved: &[&str]) -> Vec<String> {
+    let mut used: HashSet<String> = reserved.iter().map(|name| name.to_string()).collect();
+    names.enumerate()
+        .map(|(i, name)| {
+            let mut column = if name.is_empty() { format!("arg{}", i) } else { sanitize(name) };
+            while used.contains(&column) {
+                column = format!("arg_{}", column);
+            }
+            used.insert(column.clone());
+            column
+        })
+        .collect()
+}
+
+fn sanitize(name: &str) -> String {
+    name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_")
+}
+
+fn column_type(kind: &ParamType) -> DataType {
+    match kind {
+        ParamType::Uint(bits) if *bits <= 64 => DataType::UInt64,
+        ParamType::Int(bits) if *bits <= 64 => DataType::Int64,
+        ParamType::Bool => DataType::Boolean,
+        _ => DataType::Utf8,
+    }
+}
+
+fn to_value(token: &Token, column: &DataType) -> DecodedValue {
+    match (token, column) {
+        (Token::Uint(value), DataType::UInt64) => DecodedValue::UInt(value.low_u64()),
+        // Values up to 64 bits are sign-extended, so the low word is exact.
+        (Token::Int(value), DataType::Int64) => DecodedValue::Int(value.low_u64() as i64),
+        (Token::Bool(value), DataType::Boolean) => DecodedValue::Bool(*value),
+        (token, _) => DecodedValue::Text(token_text(token)),
+    }
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => signed_decimal(*value),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => value.clone(),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Array(_) | Token::FixedArray(_) | Token::Tuple(_) => token_json(token).to_string(),
+    }
+}
+
+fn token_json(token: &Token) -> Value {
+    match token {
+        Token::Array(items) | Token::FixedArray(items) | Token::Tuple(items) => {
+            Value::Array(items.iter().map(token_json).collect())
+        }
+        Token::Bool(value) => Value::Bool(*value),
+        other => Value::String(token_text(other)),
+    }
+}
+
+/// Two's complement `int256` as a decimal string.
+fn signed_decimal(value: U256) -> String {
+    if value.bit(255) {
+        format!("-{}", (!value).overflowing_add(U256::one()).0)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::datasets::{AbiDataset, Dataset};
+    use crate::models::{Log, Transaction};
+    use arrow::array::{Int64Array, StringArray, UInt64Array};
+    use serde_json::json;
+    use web3::ethabi::{self, Address};
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const POOL: &str = "0x1111111111111111111111111111111111111111";
+    const POOL_2: &str = "0x2222222222222222222222222222222222222222";
+
+    fn load(test: &str, files: &[(&str, Value)]) -> Result<AbiRegistry> {
+        let dir = std::env::temp_dir().join(format!("abi_{}_{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, abi) in files {
+            std::fs::write(dir.join(name), abi.to_string()).unwrap();
+        }
+        let registry = AbiRegistry::load(Some(&dir), ColumnTypes::Strings);
+        std::fs::remove_dir_all(&dir).unwrap();
+        registry
+    }
+
+    fn param(name: &str, kind: &str, indexed: bool) -> Value {
+        json!({ "name": name, "type": kind, "indexed": indexed })
+    }
+
+    fn event(name: &str, inputs: Vec<Value>) -> Value {
+        json!({ "type": "event", "name": name, "anonymous": false, "inputs": inputs })
+    }
+
+    fn function(name: &str, inputs: Vec<Value>) -> Value {
+        json!({ "type": "function", "name": name, "inputs": inputs, "outputs": [], "stateMutability": "nonpayable" })
+    }
+
+    fn table_names(registry: &AbiRegistry) -> Vec<&str> {
+        registry.tables().iter().map(|table| table.name.as_str()).collect()
+    }
+
+    fn columns(registry: &AbiRegistry, table: &str) -> Vec<(String, DataType)> {
+        let table = registry.tables().iter().find(|t| t.name == table).unwrap();
+        table.schema.fields().iter().map(|field| (field.name().clone(), field.data_type().clone())).collect()
+    }
+
+    fn block(logs: Vec<Log>, transactions: Vec<Transaction>) -> Block {
+        Block {
+            number: 100,
+            hash: format!("0x{:064x}", 100),
+            parent_hash: format!("0x{:064x}", 99),
+            transactions,
+            timestamp: 1_700_000_000,
+            authorizations: Vec::new(),
+            uncles: Vec::new(),
+            logs,
+            token_transfers: Vec::new(),
+            contracts: Vec::new(),
+            state_diffs: Vec::new(),
+            tokens: Vec::new(),
+            decoded: Vec::new(),
+        }
+    }
+
+    fn log(address: &str, topics: Vec<H256>, data: Vec<u8>) -> Log {
+        Log {
+            block_number: 100,
+            transaction_hash: format!("0x{:064x}", 1),
+            transaction_index: 0,
+            log_index: 3,
+            address: address.to_string(),
+            topics: topics.iter().map(|topic| format!("{:?}", topic)).collect(),
+            data: format!("0x{}", hex::encode(data)),
+            signature: None,
+        }
+    }
+
+    fn call(to: &str, input: Vec<u8>) -> Transaction {
+        Transaction {
+            hash: format!("0x{:064x}", 2),
+            from: POOL_2.to_string(),
+            to: Some(to.to_string()),
+            value: "0".to_string(),
+            input: format!("0x{}", hex::encode(input)),
+            signature: None,
+        }
+    }
+
+    fn topic(hex: &str) -> H256 {
+        H256::from_slice(&hex::decode(hex).unwrap())
+    }
+
+    fn address_topic(address: &str) -> H256 {
+        H256::from(address.parse::<Address>().unwrap())
+    }
+
+    #[test]
+    fn takes_names_and_addresses_from_files() {
+        let transfer = event("Transfer", vec![param("from", "address", true), param("to", "address", true), param("value", "uint256", false)]);
+        let registry = load("files", &[
+            // Checksummed address in the file name, named contract.
+            ("usdc_0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48.json", json!([transfer])),
+            // Bare address: the address is the name.
+            (&format!("{}.json", POOL), json!([transfer])),
+            // Object form with several deployments.
+            ("pools.json", json!({ "name": "pair", "address": [POOL_2, "0x3333333333333333333333333333333333333333"], "abi": [transfer] })),
+            // No address anywhere: skipped.
+            ("erc20.json", json!([transfer])),
+        ])
+        .unwrap();
+
+        assert_eq!(table_names(&registry), vec![
+            "0x1111111111111111111111111111111111111111_Transfer",
+            "pair_Transfer",
+            "usdc_Transfer",
+        ]);
+        let mut addresses: Vec<&str> = registry.contracts.keys().map(String::as_str).collect();
+        addresses.sort();
+        assert_eq!(addresses, vec![POOL, POOL_2, "0x3333333333333333333333333333333333333333", TOKEN]);
+        assert_eq!(registry.address_bytes.len(), 4);
+    }
+
+    #[test]
+    fn rejects_two_files_defining_the_same_table() {
+        let transfer = event("Transfer", vec![param("value", "uint256", false)]);
+        let error = load("clash", &[
+            (&format!("token_{}.json", POOL), json!([transfer])),
+            (&format!("token_{}.json", POOL_2), json!([transfer])),
+        ])
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("table token_Transfer is already defined"), "{}", error);
+    }
+
+    #[test]
+    fn splits_overloaded_events_and_functions_by_selector() {
+        let registry = load("overloads", &[(&format!("token_{}.json", TOKEN), json!([
+            event("Transfer", vec![param("from", "address", true), param("to", "address", true), param("value", "uint256", false)]),
+            event("Transfer", vec![param("from", "address", true), param("to", "address", true), param("value", "uint256", false), param("data", "bytes", false)]),
+            function("transfer", vec![param("to", "address", false), param("value", "uint256", false)]),
+            function("transfer", vec![param("to", "address", false), param("value", "uint256", false), param("data", "bytes", false)]),
+            function("approve", vec![param("spender", "address", false), param("value", "uint256", false)]),
+        ]))])
+        .unwrap();
+
+        // Suffixes are the well-known ERC-20/ERC-223 topic and selector prefixes.
+        assert_eq!(table_names(&registry), vec![
+            "token_Transfer_ddf252ad",
+            "token_Transfer_e19260af",
+            "token_call_approve",
+            "token_call_transfer_a9059cbb",
+            "token_call_transfer_be45fd62",
+        ]);
+
+        let erc223 = topic("e19260aff97b920c7df27010903aeb9c8d2be5d310a2c67824cf3f15396e4c16");
+        let data = ethabi::encode(&[Token::Uint(7.into()), Token::Bytes(vec![0xca, 0xfe])]);
+        let logs = vec![log(TOKEN, vec![erc223, address_topic(POOL), address_topic(POOL_2)], data)];
+        let mut input = hex::decode("a9059cbb").unwrap();
+        input.extend(ethabi::encode(&[Token::Address(POOL.parse().unwrap()), Token::Uint(9.into())]));
+        let rows = registry.decode(&block(logs, vec![call(TOKEN, input)]));
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].table, "token_Transfer_e19260af");
+        assert_eq!(format!("{:?}", rows[0].values), format!("{:?}", [
+            DecodedValue::Text(POOL.to_string()),
+            DecodedValue::Text(POOL_2.to_string()),
+            DecodedValue::Text("7".to_string()),
+            DecodedValue::Text("0xcafe".to_string()),
+        ]));
+        assert_eq!(rows[1].table, "token_call_transfer_a9059cbb");
+        assert_eq!(rows[1].from.as_deref(), Some(POOL_2));
+    }
+
+    #[test]
+    fn renames_parameters_that_clash_with_fixed_columns() {
+        assert_eq!(column_names(["x", "", "x", "arg_x"].into_iter(), &[]), vec!["x", "arg1", "arg_x", "arg_arg_x"]);
+
+        let registry = load("reserved", &[(&format!("vault_{}.json", POOL), json!([
+            event("Deposit", vec![
+                param("address", "address", true),
+                param("log_index", "uint64", false),
+                param("", "uint256", false),
+                param("value", "bool", false),
+            ]),
+            function("deposit", vec![param("from", "address", false), param("value", "uint256", false), param("block-number", "uint8", false)]),
+        ]))])
+        .unwrap();
+
+        let names = |table| columns(&registry, table).into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names("vault_Deposit"), vec![
+            "block_number", "transaction_hash", "log_index", "address",
+            "arg_address", "arg_log_index", "arg2", "value",
+        ]);
+        assert_eq!(names("vault_call_deposit"), vec![
+            "block_number", "transaction_hash", "address", "from", "value",
+            "arg_from", "arg_value", "arg_block_number",
+        ]);
+
+        // Renamed parameters still decode into the right columns.
+        let deposit = Contract::load(serde_json::to_vec(&json!([
+            event("Deposit", vec![param("address", "address", true), param("log_index", "uint64", false), param("", "uint256", false), param("value", "bool", false)]),
+        ])).unwrap().as_slice()).unwrap().event("Deposit").unwrap().signature();
+        let data = ethabi::encode(&[Token::Uint(4.into()), Token::Uint(5.into()), Token::Bool(true)]);
+        let rows = registry.decode(&block(vec![log(POOL, vec![deposit, address_topic(POOL_2)], data)], Vec::new()));
+        assert_eq!(format!("{:?}", rows[0].values), format!("{:?}", [
+            DecodedValue::Text(POOL_2.to_string()),
+            DecodedValue::UInt(4),
+            DecodedValue::Text("5".to_string()),
+            DecodedValue::Bool(true),
+        ]));
+    }
+
+    #[test]
+    fn keeps_only_the_topic_hash_of_indexed_dynamic_params() {
+        let registry = load("indexed", &[(&format!("names_{}.json", POOL), json!([
+            event("Registered", vec![param("name", "string", true), param("id", "uint256", true), param("label", "string", false)]),
+        ]))])
+        .unwrap();
+        assert_eq!(columns(&registry, "names_Registered")[4..], [
+            ("name".to_string(), DataType::Utf8),
+            ("id".to_string(), DataType::Utf8),
+            ("label".to_string(), DataType::Utf8),
+        ]);
+
+        let signature = registry.contracts[POOL].events.keys().next().copied().unwrap();
+        // The keccak hash in the topic is all that is left of the indexed string.
+        let name_hash = topic("af2caa1c2ca1d027f1ac823b529d0a67cd144264b2789fa2ea4d63a67c7103cc");
+        let data = ethabi::encode(&[Token::String("hello".to_string())]);
+        let rows = registry.decode(&block(vec![log(POOL, vec![signature, name_hash, H256::from_low_u64_be(42)], data)], Vec::new()));
+        assert_eq!(format!("{:?}", rows[0].values), format!("{:?}", [
+            DecodedValue::Text(format!("{:?}", name_hash)),
+            DecodedValue::Text("42".to_string()),
+            DecodedValue::Text("hello".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn converts_values_to_the_dataset_columns() {
+        let registry = load("values", &[(&format!("vault_{}.json", POOL), json!([
+            function("settle", vec![
+                param("amount", "uint64", false),
+                param("delta", "int64", false),
+                param("wide", "int256", false),
+                param("total", "uint256", false),
+                json!({ "name": "order", "type": "tuple", "components": [
+                    { "name": "maker", "type": "address" },
+                    { "name": "amounts", "type": "uint256[]" },
+                    { "name": "filled", "type": "bool" },
+                ]}),
+            ]),
+        ]))])
+        .unwrap();
+        let minus_five = Token::Int(U256::MAX - 4);
+        let tokens = [
+            Token::Uint(u64::MAX.into()),
+            minus_five.clone(),
+            minus_five,
+            Token::Uint(U256::MAX),
+            Token::Tuple(vec![
+                Token::Address(POOL_2.parse().unwrap()),
+                Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]),
+                Token::Bool(true),
+            ]),
+        ];
+        let settle = registry.contracts[POOL].functions.values().next().unwrap();
+        let input = settle.function.encode_input(&tokens).unwrap();
+        let mut block = block(Vec::new(), vec![call(POOL, input)]);
+        block.decoded = registry.decode(&block);
+
+        let table = registry.tables()[0].clone();
+        let batch = AbiDataset::new(table, ColumnTypes::Strings).build_batch(&[block]).unwrap().unwrap();
+        let column = |name| batch.column_by_name(name).unwrap().clone();
+        assert_eq!(column("amount").as_any().downcast_ref::<UInt64Array>().unwrap().value(0), u64::MAX);
+        assert_eq!(column("delta").as_any().downcast_ref::<Int64Array>().unwrap().value(0), -5);
+        let text = |name| column(name).as_any().downcast_ref::<StringArray>().unwrap().value(0).to_string();
+        assert_eq!(text("wide"), "-5");
+        assert_eq!(text("total"), U256::MAX.to_string());
+        assert_eq!(text("order"), format!(r#"["{}",["1","2"],true]"#, POOL_2));
+    }
+}
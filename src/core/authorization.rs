@@ -0,0 +1,232 @@
+use crate::core::decode::to_u64;
+use crate::models::Authorization;
+use crate::utils::error::DecodeError;
+use serde::Deserialize;
+use serde_json::Value;
+use web3::{
+    signing::{keccak256, recover},
+    types::{Address, U256, U64},
+};
+
+/// Prefix byte of the EIP-7702 authorization signing payload.
+const AUTHORIZATION_MAGIC: u8 = 0x05;
+/// Half the secp256k1 group order. Signatures with a larger `s` are
+/// malleable duplicates and invalid under EIP-2.
+const SECP256K1_HALF_N: U256 = U256([0xdfe92f46681b20a0, 0x5d576e7357a4501d, 0xffffffffffffffff, 0x7fffffffffffffff]);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcAuthorization {
+    chain_id: U256,
+    address: Address,
+    nonce: U256,
+    #[serde(alias = "v")]
+    y_parity: U64,
+    r: U256,
+    s: U256,
+}
+
+/// Decodes the `authorizationList` of a raw transaction object, if present.
+pub fn decode_authorizations(block_number: u64, tx: &Value) -> Result<Vec<Authorization>, DecodeError> {
+    let list = match tx.get("authorizationList") {
+        Some(Value::Array(list)) => list,
+        _ => return Ok(Vec::new()),
+    };
+
+    let tx_hash = tx.get("hash").and_then(Value::as_str).unwrap_or_default();
+
+    list.iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let auth = RpcAuthorization::deserialize(entry).map_err(|e| DecodeError::InvalidField {
+                field: "authorizationList",
+                reason: format!("tx {}: {}", tx_hash, e),
+            })?;
+
+            let nonce = to_u64(auth.nonce, "authorization.nonce")?;
+
+            Ok(Authorization {
+                block_number,
+                tx_hash: tx_hash.to_string(),
+                index: index as u32,
+                chain_id: auth.chain_id.to_string(),
+                address: format!("{:?}", auth.address),
+                nonce,
+                signer: recover_signer(&auth).map(|addr| format!("{:?}", addr)),
+            })
+        })
+        .collect()
+}
+
+/// Recovers the authority that signed `keccak256(0x05 || rlp([chain_id, address, nonce]))`.
+/// Returns `None` for signatures the EVM rejects: `y_parity` above 1 or a
+/// high `s`.
+fn recover_signer(auth: &RpcAuthorization) -> Option<Address> {
+    if auth.y_parity > U64::one() || auth.s > SECP256K1_HALF_N {
+        return None;
+    }
+    let message = signing_hash(auth);
+
+    let mut signature = [0u8; 64];
+    auth.r.to_big_endian(&mut signature[..32]);
+    auth.s.to_big_endian(&mut signature[32..]);
+
+    recover(&message, &signature, auth.y_parity.as_u64() as i32).ok()
+}
+
+fn signing_hash(auth: &RpcAuthorization) -> [u8; 32] {
+    let mut payload = Vec::with_capacity(1 + 64);
+    payload.push(AUTHORIZATION_MAGIC);
+    payload.extend(rlp_list(&[
+        rlp_uint(auth.chain_id),
+        rlp_bytes(auth.address.as_bytes()),
+        rlp_uint(auth.nonce),
+    ]));
+    keccak256(&payload)
+}
+
+fn rlp_uint(value: U256) -> Vec<u8> {
+    let mut buf = [0u8; 32];
+    value.to_big_endian(&mut buf);
+    let start = buf.iter().position(|b| *b != 0).unwrap_or(buf.len());
+    rlp_bytes(&buf[start..])
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut out = rlp_header(0x80, bytes.len());
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload: Vec<u8> = items.concat();
+    let mut out = rlp_header(0xc0, payload.len());
+    out.extend(payload);
+    out
+}
+
+fn rlp_header(offset: u8, len: usize) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+    let len_bytes = len.to_be_bytes();
+    let start = len_bytes.iter().position(|b| *b != 0).unwrap_or(len_bytes.len());
+    let mut out = vec![offset + 55 + (len_bytes.len() - start) as u8];
+    out.extend_from_slice(&len_bytes[start..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use web3::signing::{Key, SecretKey};
+
+    /// First Hardhat/Anvil development account.
+    const DEV_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const DEV_ADDRESS: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    fn authorization(chain_id: U256, nonce: u64) -> RpcAuthorization {
+        RpcAuthorization {
+            chain_id,
+            address: Address::repeat_byte(0x44),
+            nonce: nonce.into(),
+            y_parity: U64::zero(),
+            r: U256::zero(),
+            s: U256::zero(),
+        }
+    }
+
+    fn signed(chain_id: U256, nonce: u64) -> RpcAuthorization {
+        let key = SecretKey::from_slice(&hex::decode(DEV_KEY).unwrap()).unwrap();
+        let mut auth = authorization(chain_id, nonce);
+        let signature = (&key).sign_message(&signing_hash(&auth)).unwrap();
+        auth.y_parity = signature.v.into();
+        auth.r = U256::from_big_endian(signature.r.as_bytes());
+        auth.s = U256::from_big_endian(signature.s.as_bytes());
+        auth
+    }
+
+    #[test]
+    fn signing_hash_matches_known_payloads() {
+        // keccak256(0x05 || d7 01 94 44..44 80)
+        assert_eq!(
+            hex::encode(signing_hash(&authorization(1.into(), 0))),
+            "d3b0ec401eba5dde9f5d8ffa08f03e29df4186c5a4ef9a1e02fc2c019660c57b"
+        );
+        // keccak256(0x05 || e0 89 010000000000000000 94 44..44 2a)
+        assert_eq!(
+            hex::encode(signing_hash(&authorization(U256::one() << 64, 42))),
+            "c76045bc8f274579ce47c7d3853da034788d4b70a5080e97e26093b6f2591c75"
+        );
+    }
+
+    #[test]
+    fn recovers_signer() {
+        let auth = signed(1.into(), 7);
+        assert_eq!(format!("{:?}", recover_signer(&auth).unwrap()), DEV_ADDRESS);
+    }
+
+    #[test]
+    fn recovers_the_authority_of_an_external_vector() {
+        // A Base (chain 8453) authorization from alloy-eip7702's serde tests;
+        // the hash and authority are what its `signature_hash` and
+        // `recover_authority` give for it.
+        let tx = json!({
+            "hash": "0x01",
+            "authorizationList": [{
+                "chainId": "0x2105",
+                "address": "0x000000004F43C49e93C970E84001853a70923B03",
+                "nonce": "0x0",
+                "yParity": "0x0",
+                "r": "0xb3fdb76993ec6787313ab8b54129200032dfb9ce683fa9f7693129421e6a3185",
+                "s": "0x210b3350107a5687b532a346a90e7cc9a799b995743e2b79698bedba7bd779ae",
+            }],
+        });
+        let auth = RpcAuthorization::deserialize(&tx["authorizationList"][0]).unwrap();
+        assert_eq!(
+            hex::encode(signing_hash(&auth)),
+            "60beebce94dc381f9d8e3f7d2bfe63456ddbf1ec1e4b7533ebd08273097d75bb"
+        );
+
+        let authorizations = decode_authorizations(5, &tx).unwrap();
+        assert_eq!(authorizations[0].chain_id, "8453");
+        assert_eq!(authorizations[0].signer.as_deref(), Some("0xb187803a4ac9c5a498e470aab82de203f5870ab8"));
+    }
+
+    #[test]
+    fn rejects_high_s_and_bad_y_parity() {
+        let order = SECP256K1_HALF_N * 2 + 1;
+        let mut high_s = signed(1.into(), 7);
+        // The same point with the other `s`, which recovers the same key.
+        high_s.s = order - high_s.s;
+        high_s.y_parity = U64::one() - high_s.y_parity;
+        assert_eq!(recover_signer(&high_s), None);
+
+        let mut bad_parity = signed(1.into(), 7);
+        bad_parity.y_parity = 2.into();
+        assert_eq!(recover_signer(&bad_parity), None);
+    }
+
+    #[test]
+    fn keeps_chain_ids_beyond_u64() {
+        let tx = json!({
+            "hash": "0x01",
+            "authorizationList": [{
+                "chainId": "0x10000000000000000",
+                "address": "0x4444444444444444444444444444444444444444",
+                "nonce": "0x0",
+                "yParity": "0x0",
+                "r": "0x1",
+                "s": "0x1",
+            }],
+        });
+
+        let authorizations = decode_authorizations(5, &tx).unwrap();
+        assert_eq!(authorizations.len(), 1);
+        assert_eq!(authorizations[0].chain_id, "18446744073709551616");
+    }
+}
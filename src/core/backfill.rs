@@ -0,0 +1,134 @@
+use crate::config::Config;
+use crate::core::abi::AbiRegistry;
+use crate::core::gaps::IndexedBlocks;
+use crate::core::shutdown::Shutdown;
+use crate::core::{BlockProcessor, MetricsCollector, StorageManager};
+use anyhow::Result;
+use metrics::gauge;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use tracing::{error, info};
+
+/// History ranges still to be indexed, handed out newest first so recent
+/// history becomes available before old history.
+pub struct RangeQueue {
+    ranges: Mutex<VecDeque<(u64, u64)>>,
+}
+
+impl RangeQueue {
+    /// Splits each of the ascending, inclusive `spans` into ranges aligned to
+    /// multiples of `range_blocks`.
+    pub fn split(spans: &[(u64, u64)], range_blocks: u64) -> Self {
+        let mut ranges = VecDeque::new();
+        for &(start, end) in spans {
+            let mut range_start = start;
+            while range_start <= end {
+                let aligned_end = (range_start / range_blocks + 1)
+                    .saturating_mul(range_blocks)
+                    .saturating_sub(1);
+                let range_end = aligned_end.min(end);
+                ranges.push_front((range_start, range_end));
+                match range_end.checked_add(1) {
+                    Some(next) => range_start = next,
+                    None => break,
+                }
+            }
+        }
+
+        gauge!("backfill_ranges_remaining").set(ranges.len() as f64);
+        Self { ranges: Mutex::new(ranges) }
+    }
+
+    fn next(&self) -> Option<(u64, u64)> {
+        let mut ranges = self.ranges.lock().unwrap();
+        let range = ranges.pop_front();
+        gauge!("backfill_ranges_remaining").set(ranges.len() as f64);
+        range
+    }
+}
+
+/// Takes ranges from `queue` until it is empty, writing each one to its own
+/// files. Blocks already stored or dead-lettered by an earlier run are left
+/// out. On shutdown the current file is closed with the blocks fetched so
+/// far; on failure the range is released and the error returned.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_worker(
+    worker: usize,
+    processor: Arc<BlockProcessor>,
+    config: Config,
+    indexed: Arc<IndexedBlocks>,
+    abi: Arc<AbiRegistry>,
+    queue: Arc<RangeQueue>,
+    metrics: MetricsCollector,
+    shutdown: Shutdown,
+) -> Result<()> {
+    while let Some((start, end)) = queue.next() {
+        let missing = indexed.handled().gaps(start, end);
+        info!(
+            event = "backfill_range_started",
+            message = "Backfill worker picked up block range",
+            worker = worker,
+            start_block = start,
+            end_block = end,
+            missing_ranges = missing.len()
+        );
+
+        let result: Result<()> = async {
+            for (missing_start, missing_end) in missing {
+                let mut storage = StorageManager::new(&config, indexed.clone(), &abi, processor.tokens())?;
+                for block_number in missing_start..=missing_end {
+                    if shutdown.is_requested() {
+                        break;
+                    }
+                    if let Some(block) = processor.fetch_history_block(block_number).await? {
+                        metrics.record_block(&block);
+                        metrics.record_backfilled_block();
+                        storage.store_block(block).await?;
+                    }
+                }
+                storage.close().await?;
+                if shutdown.is_requested() {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        // Unfinished blocks become gaps again, for the gap worker or the
+        // next start.
+        indexed.release(start, end);
+
+        if let Err(e) = result {
+            error!(
+                event = "backfill_range_failed",
+                message = "Backfill worker failed on block range",
+                worker = worker,
+                start_block = start,
+                end_block = end,
+                error = %e
+            );
+            return Err(e);
+        }
+        if shutdown.is_requested() {
+            info!(
+                event = "backfill_worker_stopped",
+                message = "Shutdown requested, backfill worker stopped",
+                worker = worker,
+                start_block = start,
+                end_block = end
+            );
+            return Ok(());
+        }
+        info!(
+            event = "backfill_range_finished",
+            message = "Backfill worker finished block range",
+            worker = worker,
+            start_block = start,
+            end_block = end
+        );
+    }
+
+    Ok(())
+}
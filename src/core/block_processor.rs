@@ -1,35 +1,114 @@
-use crate::config::Config;
-use crate::models::Block;
-use crate::utils::error::IndexerError;
+use crate::config::{BlockSource, Config, HeadPollConfig, MalformedBlockPolicy, StateDiffTracer};
+use crate::models::{Block, Contract, StateDiff, Token, Uncle};
+use crate::utils::error::{DecodeError, IndexerError};
 use anyhow::Result;
 use crossbeam::channel;
-use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
-use tracing::{info, error};
+use flate2::read::MultiGzDecoder;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}};
+use tracing::{info, error, warn};
 use web3::{
-    types::{BlockNumber, BlockId},
+    helpers,
+    signing::keccak256,
+    types::{BlockId, BlockNumber, Index},
+    Transport,
     Web3,
 };
-use crate::core::MetricsCollector;
+use crate::core::abi::AbiRegistry;
+use crate::core::contracts::{receipt_creations, trace_creations};
+use crate::core::dead_letter::DeadLetterQueue;
+use crate::core::decode::{decode_block, decode_receipt_logs, logs_bloom, raw_block_number, to_u64, uncle_count};
+use crate::core::filter::BlockFilter;
+use crate::core::shutdown::Shutdown;
+use crate::core::signatures::SignatureDb;
+use crate::core::state_diffs::{decode_prestate_diffs, decode_trace_state_diffs};
+use crate::core::token_transfers::decode_token_transfers;
+use crate::core::tokens::{
+    decode_decimals, decode_text, decode_uint, TokenCache, DECIMALS_SELECTOR, NAME_SELECTOR, SYMBOL_SELECTOR,
+    TOTAL_SUPPLY_SELECTOR,
+};
+use crate::core::gaps::IndexedBlocks;
+use crate::core::head_poll::{unix_now, HeadPollScheduler};
+use crate::core::{MetricsCollector, StorageManager};
+use crate::rpc::{self, RpcTransport};
+
+/// How often the backfill worker looks for new gaps.
+const BACKFILL_SCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
 
 #[derive(Clone)]
 pub struct BlockProcessor {
-    web3_client: Web3<web3::transports::Http>,
+    web3_client: Web3<RpcTransport>,
+    rpc_endpoint: String,
+    index_uncles: bool,
+    index_logs: bool,
+    index_token_transfers: bool,
+    /// Metadata cache of known tokens; `None` when tokens are not indexed.
+    tokens: Option<Arc<TokenCache>>,
+    index_contracts: bool,
+    trace_contract_creations: bool,
+    /// Tracer to fetch state diffs with; `None` when they are not indexed.
+    state_diffs: Option<StateDiffTracer>,
+    filter: BlockFilter,
+    abi: Arc<AbiRegistry>,
+    signatures: Arc<SignatureDb>,
+    /// Cleared once the node rejects `eth_getBlockReceipts`, after which
+    /// receipts are fetched per transaction.
+    block_receipts_supported: Arc<AtomicBool>,
+    malformed_block_policy: MalformedBlockPolicy,
+    head_poll: HeadPollConfig,
+    dead_letters: Arc<DeadLetterQueue>,
+    /// Where skipped blocks are recorded so gap scans leave them out.
+    indexed: Arc<IndexedBlocks>,
     latest_block: Arc<AtomicU64>,
-    buffer_size: usize,
+    /// First block of the head loop; `u64::MAX` until it has started.
+    head_start: Arc<AtomicU64>,
+    shutdown: Shutdown,
     blocks_channel: (channel::Sender<Block>, channel::Receiver<Block>),
     metrics: MetricsCollector,
 }
 
 impl BlockProcessor {
-    pub async fn new(config: &Config, metrics: MetricsCollector) -> Result<Self> {
-        let transport = web3::transports::Http::new(&config.rpc_endpoint)?;
-        let web3_client = Web3::new(transport);
+    pub async fn new(
+        config: &Config,
+        metrics: MetricsCollector,
+        abi: Arc<AbiRegistry>,
+        indexed: Arc<IndexedBlocks>,
+        shutdown: Shutdown,
+    ) -> Result<Self> {
+        let web3_client = rpc::connect(config, &shutdown)?;
+        if config.block_source == BlockSource::Rpc {
+            rpc::verify_chain(config, &web3_client).await?;
+        }
         let blocks_channel = channel::bounded(config.blocks_in_memory);
         
         Ok(Self {
             web3_client,
+            rpc_endpoint: config.rpc_endpoint.clone(),
+            index_uncles: config.index_uncles,
+            index_logs: config.index_logs,
+            index_token_transfers: config.index_token_transfers,
+            tokens: if config.index_tokens {
+                Some(Arc::new(TokenCache::open(&config.data_dir)?))
+            } else {
+                None
+            },
+            index_contracts: config.index_contracts,
+            trace_contract_creations: config.trace_contract_creations,
+            state_diffs: config.index_state_diffs.then_some(config.state_diff_tracer),
+            filter: BlockFilter::new(&config.filter),
+            abi,
+            signatures: Arc::new(SignatureDb::load(config.signatures_path.as_deref())?),
+            block_receipts_supported: Arc::new(AtomicBool::new(true)),
+            malformed_block_policy: config.malformed_block_policy,
+            head_poll: config.head_poll.clone(),
+            dead_letters: Arc::new(DeadLetterQueue::open(&config.data_dir)?),
+            indexed,
             latest_block: Arc::new(AtomicU64::new(0)),
-            buffer_size: config.blocks_in_memory,
+            head_start: Arc::new(AtomicU64::new(u64::MAX)),
+            shutdown,
             blocks_channel,
             metrics,
         })
@@ -39,7 +118,7 @@ impl BlockProcessor {
         info!(
             event = "fetching_latest_block",
             message = "Attempting to get latest block number",
-            rpc_endpoint = self.web3_client.enode()
+            rpc_endpoint = %self.rpc_endpoint
         );
     
         match self.web3_client.eth().block_number().await {
@@ -62,31 +141,379 @@ impl BlockProcessor {
             }
         }
     }
+
+    /// Checks that the chain still builds on a stored block before resuming
+    /// after it: `block`'s parent must be `parent_hash` or, if `block` does
+    /// not exist yet, the block before it must still have that hash.
+    pub async fn verify_parent(&self, block: u64, parent_hash: &str) -> Result<()> {
+        let actual = match self.fetch_header(block).await? {
+            Some(header) => header_hash(&header, "parentHash"),
+            None => self.fetch_header(block - 1).await?.and_then(|header| header_hash(&header, "hash")),
+        };
+        if actual.as_deref() != Some(parent_hash) {
+            return Err(IndexerError::SourceError(format!(
+                "Stored block {} has hash {} but the chain has {}; set START_BLOCK to choose where to resume",
+                block - 1,
+                parent_hash,
+                actual.as_deref().unwrap_or("no such block")
+            )).into());
+        }
+        Ok(())
+    }
+
+    async fn fetch_header(&self, block_number: u64) -> Result<Option<Value>> {
+        let header = self.web3_client
+            .transport()
+            .execute("eth_getBlockByNumber", vec![
+                helpers::serialize(&BlockNumber::Number(block_number.into())),
+                helpers::serialize(&false),
+            ])
+            .await
+            .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+        Ok((!header.is_null()).then_some(header))
+    }
+
     async fn fetch_block(&self, block_number: u64) -> Result<Block> {
-        let block = self.web3_client
-            .eth()
-            .block_with_txs(BlockId::Number(BlockNumber::Number(block_number.into())))
+        // Fetched as raw JSON so fields web3 does not model, such as the
+        // EIP-7702 `authorizationList`, remain available for decoding.
+        let raw = self.web3_client
+            .transport()
+            .execute("eth_getBlockByNumber", vec![
+                helpers::serialize(&BlockNumber::Number(block_number.into())),
+                helpers::serialize(&true),
+            ])
             .await
-            .map_err(|e| IndexerError::RpcError(e.to_string()))?
-            .ok_or_else(|| IndexerError::RpcError("Block not found".into()))?;
-
-        let transactions = block.transactions.into_iter()
-            .map(|tx| crate::models::Transaction {
-                hash: format!("{:?}", tx.hash),
-                from: format!("{:?}", tx.from),
-                to: tx.to.map(|addr| format!("{:?}", addr)),
-                value: tx.value.to_string(),
+            .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+
+        if raw.is_null() {
+            return Err(IndexerError::RpcError("Block not found".into()).into());
+        }
+
+        let mut block = match decode_block(&raw) {
+            Ok(block) => block,
+            Err(error) => {
+                return Err(IndexerError::MalformedBlock {
+                    number: block_number,
+                    error,
+                    raw: Box::new(raw),
+                }.into());
+            }
+        };
+
+        let uncles = uncle_count(&raw);
+        if self.index_uncles && uncles > 0 {
+            block.uncles = self.fetch_uncles(block_number, uncles).await?;
+        }
+
+        let all_logs = self.index_logs || self.index_token_transfers || self.tokens.is_some();
+        let bloom = logs_bloom(&raw);
+        let needs_logs = self.abi.needs_receipts(&block, bloom.as_deref())
+            || self.filter.needs_receipts(&block, bloom.as_deref(), all_logs);
+        // Traces cover top-level deployments too, so receipts are only
+        // needed for contracts when tracing is off.
+        let needs_creations = self.index_contracts
+            && !self.trace_contract_creations
+            && block.transactions.iter().any(|tx| tx.to.is_none());
+        let receipts = if needs_logs || needs_creations {
+            self.fetch_receipts(&block).await?
+        } else {
+            Vec::new()
+        };
+        if needs_logs {
+            block.logs = decode_receipt_logs(block.number, &receipts)?;
+        }
+        if let Some(tracer) = self.state_diffs.filter(|_| !block.transactions.is_empty()) {
+            block.state_diffs = self.fetch_state_diffs(&block, tracer).await?;
+        }
+        self.filter.apply(&mut block);
+        self.signatures.annotate(&mut block);
+        if self.index_token_transfers || self.tokens.is_some() {
+            block.token_transfers = decode_token_transfers(&block.logs);
+        }
+        if self.index_contracts {
+            block.contracts = self.fetch_contracts(&block, &receipts).await?;
+        }
+        // Last fallible step: claimed tokens are only released by storage.
+        if let Some(cache) = &self.tokens {
+            block.tokens = self.discover_tokens(&block, cache).await?;
+        }
+        block.decoded = self.abi.decode(&block);
+
+        Ok(block)
+    }
+
+    /// Contracts deployed by the block's remaining transactions, from
+    /// `trace_block` when enabled and from `receipts` otherwise.
+    async fn fetch_contracts(&self, block: &Block, receipts: &[Value]) -> Result<Vec<Contract>> {
+        let creations = if self.trace_contract_creations {
+            let traces = self.web3_client
+                .transport()
+                .execute("trace_block", vec![
+                    helpers::serialize(&BlockNumber::Number(block.number.into())),
+                ])
+                .await
+                .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+            let traces = traces.as_array().ok_or_else(|| {
+                IndexerError::RpcError(format!("Unexpected trace_block result for block {}: {}", block.number, traces))
+            })?;
+            trace_creations(block, traces)
+        } else {
+            receipt_creations(block, receipts)
+        };
+
+        let codes = creations.iter().map(|creation| self.fetch_code(block.number, &creation.address));
+        let codes = futures::future::try_join_all(codes).await?;
+
+        Ok(creations.into_iter()
+            .zip(codes)
+            .map(|(creation, code)| Contract {
+                block_number: block.number,
+                transaction_hash: creation.transaction_hash,
+                creator: creation.creator,
+                address: creation.address,
+                internal: creation.internal,
+                code: format!("0x{}", hex::encode(&code)),
+                code_hash: format!("0x{}", hex::encode(keccak256(&code))),
             })
-            .collect();
+            .collect())
+    }
+
+    /// Reads the metadata of tokens in the block's transfers that are not in
+    /// `cache` yet, at this block, and claims them. The claims are committed
+    /// or released by the storage that writes the block.
+    async fn discover_tokens(&self, block: &Block, cache: &TokenCache) -> Result<Vec<Token>> {
+        let mut seen = std::collections::HashSet::new();
+        let mut tokens = Vec::new();
+
+        for transfer in &block.token_transfers {
+            let address = &transfer.token_address;
+            if !seen.insert(address) || cache.contains(address) {
+                continue;
+            }
+
+            let getters = futures::try_join!(
+                self.call_getter(block.number, address, NAME_SELECTOR),
+                self.call_getter(block.number, address, SYMBOL_SELECTOR),
+                self.call_getter(block.number, address, DECIMALS_SELECTOR),
+                self.call_getter(block.number, address, TOTAL_SUPPLY_SELECTOR),
+            );
+            let (name, symbol, decimals, total_supply) = match getters {
+                Ok(outputs) => outputs,
+                Err(e) => {
+                    cache.release(tokens.iter().map(|token: &Token| token.address.as_str()));
+                    return Err(e);
+                }
+            };
+            let token = Token {
+                address: address.clone(),
+                standard: transfer.standard,
+                block_number: block.number,
+                name: name.as_deref().and_then(decode_text),
+                symbol: symbol.as_deref().and_then(decode_text),
+                decimals: decimals.as_deref().and_then(decode_decimals),
+                total_supply: total_supply.as_deref().and_then(decode_uint).map(|supply| supply.to_string()),
+            };
+
+            if cache.claim(&token.address) {
+                info!(
+                    event = "token_discovered",
+                    message = "Read metadata of new token",
+                    address = %token.address,
+                    block_number = block.number,
+                    symbol = token.symbol.as_deref().unwrap_or_default()
+                );
+                tokens.push(token);
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    /// Output of a parameterless view call, or `None` if the contract
+    /// reverted or returned nothing.
+    async fn call_getter(&self, block_number: u64, address: &str, selector: [u8; 4]) -> Result<Option<Vec<u8>>> {
+        let result = self.web3_client
+            .transport()
+            .execute("eth_call", vec![
+                json!({ "to": address, "data": format!("0x{}", hex::encode(selector)) }),
+                helpers::serialize(&BlockNumber::Number(block_number.into())),
+            ])
+            .await;
 
-        Ok(Block {
-            number: block.number.unwrap().as_u64(),
-            hash: format!("{:?}", block.hash.unwrap()),
-            transactions,
-            timestamp: block.timestamp.as_u64(),
+        match result {
+            Ok(Value::String(output)) => {
+                Ok(hex::decode(output.trim_start_matches("0x")).ok().filter(|output| !output.is_empty()))
+            }
+            Ok(_) => Ok(None),
+            Err(web3::Error::Rpc(_)) => Ok(None),
+            Err(e) => Err(IndexerError::RpcError(e.to_string()).into()),
+        }
+    }
+
+    async fn fetch_state_diffs(&self, block: &Block, tracer: StateDiffTracer) -> Result<Vec<StateDiff>> {
+        let number = helpers::serialize(&BlockNumber::Number(block.number.into()));
+        let (method, params) = match tracer {
+            StateDiffTracer::Trace => ("trace_replayBlockTransactions", vec![number, json!(["stateDiff"])]),
+            StateDiffTracer::Prestate => ("debug_traceBlockByNumber", vec![
+                number,
+                json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }),
+            ]),
+        };
+        let result = self.web3_client
+            .transport()
+            .execute(method, params)
+            .await
+            .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+
+        Ok(match tracer {
+            StateDiffTracer::Trace => decode_trace_state_diffs(block, &result)?,
+            StateDiffTracer::Prestate => decode_prestate_diffs(block, &result)?,
         })
     }
 
+    async fn fetch_code(&self, block_number: u64, address: &str) -> Result<Vec<u8>> {
+        let code = self.web3_client
+            .transport()
+            .execute("eth_getCode", vec![
+                Value::String(address.to_string()),
+                helpers::serialize(&BlockNumber::Number(block_number.into())),
+            ])
+            .await
+            .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+
+        code.as_str()
+            .and_then(|code| hex::decode(code.trim_start_matches("0x")).ok())
+            .ok_or_else(|| {
+                DecodeError::InvalidField { field: "code", reason: format!("not hex bytes: {}", code) }.into()
+            })
+    }
+
+    async fn fetch_receipts(&self, block: &Block) -> Result<Vec<Value>> {
+        let transport = self.web3_client.transport();
+
+        if self.block_receipts_supported.load(Ordering::Relaxed) {
+            let result = transport
+                .execute("eth_getBlockReceipts", vec![
+                    helpers::serialize(&BlockNumber::Number(block.number.into())),
+                ])
+                .await;
+
+            match result {
+                Ok(Value::Array(receipts)) => return Ok(receipts),
+                Ok(other) => {
+                    return Err(IndexerError::RpcError(format!(
+                        "Unexpected eth_getBlockReceipts result for block {}: {}",
+                        block.number, other
+                    )).into());
+                }
+                // The node does not implement the method. Any other error,
+                // such as a rate limit or timeout, is retried as usual.
+                Err(web3::Error::Rpc(e)) if method_not_found(&e) => {
+                    warn!(
+                        event = "block_receipts_unsupported",
+                        message = "eth_getBlockReceipts rejected, fetching receipts per transaction",
+                        error = %e
+                    );
+                    self.block_receipts_supported.store(false, Ordering::Relaxed);
+                }
+                Err(e) => return Err(IndexerError::RpcError(e.to_string()).into()),
+            }
+        }
+
+        let requests = block.transactions.iter().map(|tx| {
+            transport.execute("eth_getTransactionReceipt", vec![Value::String(tx.hash.clone())])
+        });
+        futures::future::try_join_all(requests)
+            .await
+            .map_err(|e| IndexerError::RpcError(e.to_string()).into())
+    }
+
+    async fn fetch_uncles(&self, block_number: u64, count: usize) -> Result<Vec<Uncle>> {
+        let mut uncles = Vec::with_capacity(count);
+
+        for index in 0..count {
+            let uncle = self.web3_client
+                .eth()
+                .uncle(BlockId::Number(BlockNumber::Number(block_number.into())), Index::from(index))
+                .await
+                .map_err(|e| IndexerError::RpcError(e.to_string()))?
+                .ok_or_else(|| IndexerError::RpcError(format!("Uncle {} of block {} not found", index, block_number)))?;
+
+            let number = uncle.number.ok_or(DecodeError::MissingField("uncle.number"))?;
+            let hash = uncle.hash.ok_or(DecodeError::MissingField("uncle.hash"))?;
+
+            uncles.push(Uncle {
+                block_number,
+                uncle_index: index as u32,
+                number: number.as_u64(),
+                hash: format!("{:?}", hash),
+                parent_hash: format!("{:?}", uncle.parent_hash),
+                miner: format!("{:?}", uncle.author),
+                timestamp: to_u64(uncle.timestamp, "uncle.timestamp")?,
+                difficulty: uncle.difficulty.to_string(),
+                gas_limit: to_u64(uncle.gas_limit, "uncle.gasLimit")?,
+                gas_used: to_u64(uncle.gas_used, "uncle.gasUsed")?,
+            });
+        }
+
+        Ok(uncles)
+    }
+
+    /// Writes a malformed block to the dead-letter file and applies the
+    /// configured policy. Returns `true` if the block should be skipped, in
+    /// which case it is recorded as handled.
+    fn handle_malformed_block(
+        &self,
+        number: Option<u64>,
+        source: &str,
+        error: &DecodeError,
+        raw: &Value,
+        retryable: bool,
+    ) -> Result<bool> {
+        let policy = match self.malformed_block_policy {
+            MalformedBlockPolicy::Retry if !retryable => MalformedBlockPolicy::Skip,
+            policy => policy,
+        };
+        let action = match policy {
+            MalformedBlockPolicy::Retry => "retry",
+            MalformedBlockPolicy::Skip => "skip",
+            MalformedBlockPolicy::Abort => "abort",
+        };
+
+        if let Err(e) = self.dead_letters.record(number, source, error, action, raw) {
+            error!(
+                event = "dead_letter_error",
+                message = "Failed to write malformed block to dead-letter file",
+                error = %e,
+                block_number = ?number
+            );
+        }
+        self.metrics.record_malformed_block(action);
+
+        warn!(
+            event = "malformed_block",
+            message = "Block could not be decoded",
+            error = %error,
+            block_number = ?number,
+            source = source,
+            action = action
+        );
+
+        match policy {
+            MalformedBlockPolicy::Retry => Ok(false),
+            MalformedBlockPolicy::Skip => {
+                if let Some(number) = number {
+                    self.indexed.mark_skipped(number)?;
+                }
+                Ok(true)
+            }
+            MalformedBlockPolicy::Abort => Err(IndexerError::SourceError(format!(
+                "Aborting on malformed block {:?} from {}: {}",
+                number, source, error
+            )).into()),
+        }
+    }
+
     pub async fn process_blocks(&self, start_block: Option<u64>) -> Result<()> {
         info!(
             event = "block_processing_started",
@@ -115,6 +542,7 @@ impl BlockProcessor {
         };
 
         self.latest_block.store(current_block, Ordering::SeqCst);
+        self.head_start.store(current_block, Ordering::SeqCst);
         
         info!(
             event = "processing_loop_started",
@@ -122,7 +550,10 @@ impl BlockProcessor {
             current_block = current_block
         );
 
-        loop {
+        let mut scheduler = HeadPollScheduler::new(&self.head_poll);
+        let mut caught_up = false;
+
+        while !self.shutdown.is_requested() {
             let start_time = std::time::Instant::now();
             let latest_block = match self.get_latest_block_number().await {
                 Ok(block) => block,
@@ -132,7 +563,7 @@ impl BlockProcessor {
                         message = "Failed to get latest block number",
                         error = %e
                     );
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                    self.shutdown.sleep(tokio::time::Duration::from_secs(1)).await;
                     continue;
                 }
             };
@@ -147,11 +578,23 @@ impl BlockProcessor {
 
             self.metrics.record_sync_status(current_block, latest_block);
 
-            while current_block <= latest_block {
+            let found_new_block = latest_block >= current_block;
+            scheduler.record_poll(found_new_block);
+            // Only blocks that appear while following the head say anything
+            // about detection latency; backlog blocks are old by definition.
+            let detected_at = (caught_up && found_new_block).then(unix_now);
+
+            while current_block <= latest_block && !self.shutdown.is_requested() {
                 match self.fetch_block(current_block).await {
                     Ok(block) => {
-                        match self.blocks_channel.0.send(block.clone()) {
+                        match self.send_block(block.clone()).await {
                             Ok(_) => {
+                                scheduler.observe_block(block.number, block.timestamp);
+                                if let Some(detected_at) = detected_at.filter(|_| current_block == latest_block) {
+                                    self.metrics.record_head_detection_latency(
+                                        detected_at.saturating_sub(std::time::Duration::from_secs(block.timestamp)),
+                                    );
+                                }
                                 self.metrics.record_block(&block);
                                 self.metrics.record_processing_time(start_time);
                                 
@@ -164,6 +607,7 @@ impl BlockProcessor {
                                 self.latest_block.store(current_block, Ordering::SeqCst);
                                 current_block += 1;
                             },
+                            Err(_) if self.shutdown.is_requested() => break,
                             Err(e) => {
                                 error!(
                                     event = "channel_send_error",
@@ -176,31 +620,402 @@ impl BlockProcessor {
                         }
                     }
                     Err(e) => {
+                        if let Some(IndexerError::MalformedBlock { number, error, raw }) = e.downcast_ref() {
+                            if self.handle_malformed_block(Some(*number), "rpc", error, raw, true)? {
+                                current_block += 1;
+                                continue;
+                            }
+                        } else {
+                            error!(
+                                event = "block_fetch_error",
+                                message = "Failed to fetch block",
+                                error = %e,
+                                block_number = current_block
+                            );
+                        }
+                        self.shutdown.sleep(tokio::time::Duration::from_secs(1)).await;
+                    }
+                }
+            }
+            if self.shutdown.is_requested() {
+                break;
+            }
+
+            caught_up = true;
+            let block_interval = scheduler.block_interval();
+            let delay = scheduler.next_poll_delay();
+            self.metrics.record_head_poll(block_interval, delay);
+
+            info!(
+                event = "sync_complete",
+                message = "Caught up to latest block, waiting for new blocks",
+                current_block = current_block,
+                block_interval_ms = block_interval.map(|interval| interval.as_millis() as u64),
+                next_poll_ms = delay.as_millis() as u64
+            );
+            self.shutdown.sleep(delay).await;
+        }
+
+        info!(
+            event = "block_processing_stopped",
+            message = "Shutdown requested, stopped following the head",
+            next_block = current_block
+        );
+        Ok(())
+    }
+
+    /// Refetches blocks missing between the first indexed block and the
+    /// head loop's start, rescanning periodically for new holes such as
+    /// skipped blocks or batches lost with an unfinished file. Each gap is
+    /// written to its own files so the head's open window is left alone.
+    pub async fn backfill_gaps(&self, config: Config, indexed: Arc<IndexedBlocks>, abi: Arc<AbiRegistry>) -> Result<()> {
+        while !self.shutdown.is_requested() {
+            let head_start = self.head_start.load(Ordering::SeqCst);
+            let known = indexed.snapshot();
+            let (Some(first), false) = (known.first(), head_start == u64::MAX) else {
+                self.shutdown.sleep(BACKFILL_SCAN_INTERVAL).await;
+                continue;
+            };
+
+            let upper = known.last().unwrap_or(first).max(head_start.saturating_sub(1));
+            let gaps = known.gaps(first, upper);
+            self.metrics.record_gaps(
+                gaps.len(),
+                gaps.iter().map(|(start, end)| end - start + 1).sum(),
+            );
+            if !gaps.is_empty() {
+                info!(
+                    event = "backfill_started",
+                    message = "Backfilling missing blocks",
+                    gap_count = gaps.len(),
+                    first_gap = gaps[0].0
+                );
+            }
+
+            for (start, end) in gaps {
+                if self.shutdown.is_requested() {
+                    break;
+                }
+                let mut storage = StorageManager::new(&config, indexed.clone(), &abi, self.tokens())?;
+                for block_number in start..=end {
+                    if self.shutdown.is_requested() {
+                        break;
+                    }
+                    match self.fetch_block(block_number).await {
+                        Ok(block) => {
+                            self.metrics.record_block(&block);
+                            self.metrics.record_backfilled_block();
+                            storage.store_block(block).await?;
+                        }
+                        Err(e) => {
+                            if let Some(IndexerError::MalformedBlock { number, error, raw }) = e.downcast_ref() {
+                                self.handle_malformed_block(Some(*number), "rpc", error, raw, false)?;
+                            } else {
+                                warn!(
+                                    event = "backfill_fetch_error",
+                                    message = "Failed to backfill block, will retry on next scan",
+                                    error = %e,
+                                    block_number = block_number
+                                );
+                            }
+                        }
+                    }
+                }
+                storage.close().await?;
+            }
+
+            self.shutdown.sleep(BACKFILL_SCAN_INTERVAL).await;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches a block below the head, retrying RPC failures until it
+    /// arrives. Returns `None` if the malformed block policy skips it or
+    /// shutdown is requested while retrying.
+    pub async fn fetch_history_block(&self, block_number: u64) -> Result<Option<Block>> {
+        loop {
+            match self.fetch_block(block_number).await {
+                Ok(block) => return Ok(Some(block)),
+                Err(e) => {
+                    if let Some(IndexerError::MalformedBlock { number, error, raw }) = e.downcast_ref() {
+                        if self.handle_malformed_block(Some(*number), "rpc", error, raw, true)? {
+                            return Ok(None);
+                        }
+                    } else {
                         error!(
                             event = "block_fetch_error",
                             message = "Failed to fetch block",
                             error = %e,
-                            block_number = current_block
+                            block_number = block_number
                         );
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
                     }
+                    if self.shutdown.sleep(tokio::time::Duration::from_secs(1)).await {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends without blocking the runtime thread while the channel is full.
+    /// Gives up once shutdown is requested, as storage may have stopped.
+    async fn send_block(&self, mut block: Block) -> Result<()> {
+        loop {
+            match self.blocks_channel.0.try_send(block) {
+                Ok(()) => return Ok(()),
+                Err(channel::TrySendError::Full(returned)) => {
+                    block = returned;
+                    if self.shutdown.sleep(tokio::time::Duration::from_millis(10)).await {
+                        return Err(IndexerError::SourceError("Shutdown requested before block was stored".into()).into());
+                    }
+                }
+                Err(channel::TrySendError::Disconnected(_)) => {
+                    return Err(IndexerError::SourceError("Block channel closed".into()).into());
+                }
+            }
+        }
+    }
+
+    /// Reads blocks from local JSONL dumps instead of RPC, feeding the same
+    /// channel. Returns once every file has been read.
+    pub async fn process_files(&self, path: &Path, start_block: Option<u64>) -> Result<()> {
+        // File reads and channel sends block, so keep them off the runtime
+        // workers the storage task runs on.
+        let processor = self.clone();
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || processor.read_dumps(&path, start_block)).await?
+    }
+
+    fn read_dumps(&self, path: &Path, start_block: Option<u64>) -> Result<()> {
+        let files = dump_files(path)?;
+        info!(
+            event = "file_processing_started",
+            message = "Reading blocks from dump files",
+            path = %path.display(),
+            files = files.len(),
+            start_block = ?start_block,
+        );
+
+        for file in files {
+            let reader = open_dump(&file)?;
+
+            for (index, value) in dump_entries(reader).enumerate() {
+                if self.shutdown.is_requested() {
+                    info!(
+                        event = "file_processing_stopped",
+                        message = "Shutdown requested, stopped reading dump files",
+                        file = %file.display(),
+                        entry = index + 1
+                    );
+                    return Ok(());
+                }
+
+                let start_time = std::time::Instant::now();
+                let value = value.map_err(|e| IndexerError::SourceError(format!("{}: {}", file.display(), e)))?;
+                let raw = dump_block(&value);
+                if raw.is_null() {
+                    warn!(
+                        event = "empty_dump_entry",
+                        message = "Skipping null block in dump",
+                        file = %file.display(),
+                        entry = index + 1
+                    );
+                    continue;
+                }
+
+                let mut block = match decode_block(raw) {
+                    Ok(block) => block,
+                    Err(error) => {
+                        let source = format!("{}:{}", file.display(), index + 1);
+                        self.handle_malformed_block(raw_block_number(raw), &source, &error, raw, false)?;
+                        continue;
+                    }
+                };
+                if start_block.is_some_and(|start| block.number < start) {
+                    continue;
                 }
+                self.filter.apply(&mut block);
+                self.signatures.annotate(&mut block);
+                block.decoded = self.abi.decode(&block);
+
+                let block_number = block.number;
+                let mut pending = block.clone();
+                loop {
+                    match self.blocks_channel.0.send_timeout(pending, std::time::Duration::from_millis(100)) {
+                        Ok(()) => break,
+                        // Storage may have stopped, so do not wait on it.
+                        Err(channel::SendTimeoutError::Timeout(_)) if self.shutdown.is_requested() => {
+                            info!(
+                                event = "file_processing_stopped",
+                                message = "Shutdown requested, stopped reading dump files",
+                                file = %file.display(),
+                                entry = index + 1
+                            );
+                            return Ok(());
+                        }
+                        Err(channel::SendTimeoutError::Timeout(returned)) => pending = returned,
+                        Err(e) => return Err(IndexerError::SourceError(e.to_string()).into()),
+                    }
+                }
+                self.metrics.record_block(&block);
+                self.metrics.record_processing_time(start_time);
+                self.latest_block.store(block_number, Ordering::SeqCst);
             }
 
             info!(
-                event = "sync_complete",
-                message = "Caught up to latest block, waiting for new blocks",
-                current_block = current_block
+                event = "dump_file_processed",
+                message = "Finished reading dump file",
+                file = %file.display(),
+                latest_block = self.latest_block.load(Ordering::SeqCst)
             );
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
+
+        Ok(())
+    }
+
+    pub fn client(&self) -> &Web3<RpcTransport> {
+        &self.web3_client
+    }
+
+    pub fn tokens(&self) -> Option<Arc<TokenCache>> {
+        self.tokens.clone()
     }
 
     pub fn get_blocks_receiver(&self) -> channel::Receiver<Block> {
         self.blocks_channel.1.clone()
     }
+}
+
+/// Whether a JSON-RPC error says the method does not exist, by code or,
+/// for nodes that use a generic code, by message.
+fn method_not_found(error: &jsonrpc_core::Error) -> bool {
+    error.code == jsonrpc_core::ErrorCode::MethodNotFound
+        || error.message.to_lowercase().contains("method not found")
+}
 
-    pub fn get_latest_processed_block(&self) -> u64 {
-        self.latest_block.load(Ordering::SeqCst)
+/// Dump files under `path` in name order: the file itself, or every
+/// `.json`, `.jsonl` and `.gz` file in the directory.
+fn dump_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
     }
-}
\ No newline at end of file
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| {
+            matches!(
+                file.extension().and_then(|ext| ext.to_str()),
+                Some("json" | "jsonl" | "gz")
+            )
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Entries of a dump file. Values may be one per line, as in JSONL, or
+/// pretty printed, and a top-level array holds one entry per element.
+fn dump_entries(reader: impl Read) -> impl Iterator<Item = serde_json::Result<Value>> {
+    serde_json::Deserializer::from_reader(reader)
+        .into_iter::<Value>()
+        .flat_map(|value| match value {
+            Ok(Value::Array(entries)) => entries.into_iter().map(Ok).collect(),
+            other => vec![other],
+        })
+}
+
+/// The block of a dump entry, which is either a bare block object or a full
+/// JSON-RPC response.
+fn dump_block(value: &Value) -> &Value {
+    match value.get("result") {
+        Some(result) if value.get("jsonrpc").is_some() => result,
+        _ => value,
+    }
+}
+
+fn open_dump(path: &Path) -> Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if path.extension().and_then(|ext| ext.to_str()) == Some("gz") {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+fn header_hash(header: &Value, field: &str) -> Option<String> {
+    header.get(field).and_then(Value::as_str).map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn block(number: u64) -> Value {
+        json!({ "number": format!("0x{:x}", number) })
+    }
+
+    fn numbers(entries: impl Iterator<Item = serde_json::Result<Value>>) -> Vec<Option<u64>> {
+        entries.map(|entry| raw_block_number(dump_block(&entry.unwrap()))).collect()
+    }
+
+    #[test]
+    fn reads_jsonl_responses_and_bare_blocks() {
+        let dump = format!(
+            "{}\n\n{}\n{}\n",
+            json!({ "jsonrpc": "2.0", "id": 1, "result": block(1) }),
+            block(2),
+            json!({ "jsonrpc": "2.0", "id": 3, "result": null }),
+        );
+        let entries: Vec<Value> = dump_entries(dump.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(raw_block_number(dump_block(&entries[0])), Some(1));
+        assert_eq!(raw_block_number(dump_block(&entries[1])), Some(2));
+        assert!(dump_block(&entries[2]).is_null());
+    }
+
+    #[test]
+    fn reads_pretty_printed_and_array_dumps() {
+        let pretty = format!("{}\n{}", serde_json::to_string_pretty(&block(1)).unwrap(), block(2));
+        assert_eq!(numbers(dump_entries(pretty.as_bytes())), vec![Some(1), Some(2)]);
+
+        let array = serde_json::to_string_pretty(&json!([
+            { "jsonrpc": "2.0", "id": 3, "result": block(3) },
+            block(4),
+        ]))
+        .unwrap();
+        assert_eq!(numbers(dump_entries(array.as_bytes())), vec![Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        let entries: Vec<_> = dump_entries(format!("{}\n{{\"number\":", block(1)).as_bytes()).collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_ok());
+        assert!(entries[1].is_err());
+    }
+
+    #[test]
+    fn lists_and_opens_gzipped_dumps_in_name_order() {
+        let dir = std::env::temp_dir().join(format!("indexer_dumps_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut gz = GzEncoder::new(File::create(dir.join("b.jsonl.gz")).unwrap(), Compression::default());
+        writeln!(gz, "{}\n{}", block(2), block(3)).unwrap();
+        gz.finish().unwrap();
+        std::fs::write(dir.join("a.json"), block(1).to_string()).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a dump").unwrap();
+
+        let files = dump_files(&dir).unwrap();
+        let names: Vec<_> = files.iter().map(|file| file.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, vec!["a.json", "b.jsonl.gz"]);
+        let read: Vec<_> = files.iter()
+            .flat_map(|file| numbers(dump_entries(open_dump(file).unwrap())))
+            .collect();
+        assert_eq!(read, vec![Some(1), Some(2), Some(3)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
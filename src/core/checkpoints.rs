@@ -0,0 +1,625 @@
+use crate::core::gaps::{IndexedBlocks, RangeSet};
+use crate::utils::error::IndexerError;
+use anyhow::{anyhow, Result};
+use arrow::datatypes::Schema;
+use metrics::counter;
+use parquet::{
+    arrow::{
+        arrow_reader::{ArrowReaderMetadata, ParquetRecordBatchReaderBuilder},
+        encode_arrow_schema, ArrowWriter, ARROW_SCHEMA_META_KEY,
+    },
+    file::{
+        metadata::{ParquetMetaData, ParquetMetaDataReader, RowGroupMetaData},
+        properties::WriterProperties,
+        serialized_reader::SerializedFileReader,
+    },
+    format::{FileMetaData, KeyValue},
+    schema::types::to_thrift,
+    thrift::{TCompactOutputProtocol, TSerializable},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{info, warn};
+
+/// Suffix of parquet files that are still being written.
+pub const TEMP_SUFFIX: &str = ".parquet.tmp";
+const JOURNAL_SUFFIX: &str = ".checkpoints.jsonl";
+/// Suffix of files being rewritten from a crashed window's temporary files.
+const PARTIAL_SUFFIX: &str = ".parquet.partial";
+/// Directory in `data_dir` that unrecoverable temporary files are moved to.
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// One journal line, appended after every batch flush.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    /// Blocks the flush wrote.
+    blocks: Vec<(u64, u64)>,
+    files: Vec<CheckpointFile>,
+}
+
+/// Row groups a flush added to one temporary file.
+#[derive(Serialize, Deserialize)]
+pub struct CheckpointFile {
+    name: String,
+    /// Length the file needs for these row groups to be complete.
+    bytes: u64,
+    /// Length of the file known to be on disk when this was journaled. The
+    /// parquet writer buffers a few kilobytes, so row groups often only
+    /// reach the disk with a later flush.
+    synced: u64,
+    /// Hex-encoded parquet footer describing only these row groups.
+    footer: String,
+}
+
+impl CheckpointFile {
+    /// Describes the row groups `writer` flushed after the first
+    /// `recorded`, or `None` if there are none, after syncing what reached
+    /// the file. The Arrow schema is stored with the first ones so recovered
+    /// files keep their column types.
+    pub fn new(path: &Path, writer: &ArrowWriter<File>, recorded: usize, schema: &Schema) -> Result<Option<Self>> {
+        let row_groups = &writer.flushed_row_groups()[recorded..];
+        let Some(first) = row_groups.first() else {
+            return Ok(None);
+        };
+        let key_value_metadata = (recorded == 0)
+            .then(|| vec![KeyValue::new(ARROW_SCHEMA_META_KEY.to_string(), encode_arrow_schema(schema))]);
+        let footer = FileMetaData::new(
+            1,
+            to_thrift(first.schema_descr().root_schema())?,
+            row_groups.iter().map(RowGroupMetaData::num_rows).sum(),
+            row_groups.iter().map(RowGroupMetaData::to_thrift).collect(),
+            key_value_metadata,
+            None,
+            None,
+            None,
+            None,
+        );
+        let mut bytes = Vec::new();
+        footer.write_to_out_protocol(&mut TCompactOutputProtocol::new(&mut bytes))?;
+
+        // The journal must never vouch for bytes a power loss can take back.
+        let file = writer.inner();
+        file.sync_data()?;
+
+        Ok(Some(Self {
+            name: file_name(path)?.to_string(),
+            bytes: writer.bytes_written() as u64,
+            synced: file.metadata()?.len(),
+            footer: hex::encode(bytes),
+        }))
+    }
+}
+
+/// Records what each flush added to a rotation window's temporary files so
+/// their complete row groups can be recovered after a crash. Removed once
+/// the files are published.
+pub struct CheckpointJournal {
+    key: String,
+    path: PathBuf,
+    file: File,
+}
+
+impl CheckpointJournal {
+    /// Creates the journal of the files named `<dataset>_<key>`, or returns
+    /// `None` if another writer already holds that key.
+    pub fn create(data_dir: &Path, key: &str) -> Result<Option<Self>> {
+        let path = data_dir.join(format!("window_{}{}", key, JOURNAL_SUFFIX));
+        match File::options().write(true).create_new(true).open(&path) {
+            Ok(file) => Ok(Some(Self { key: key.to_string(), file, path })),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn append(&mut self, blocks: &RangeSet, files: Vec<CheckpointFile>) -> Result<()> {
+        let checkpoint = Checkpoint { blocks: blocks.iter().collect(), files };
+        let mut line = serde_json::to_vec(&checkpoint)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        // The rows this line vouches for are only recovered if it survives.
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    pub fn remove(self) -> Result<()> {
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Flushes a file's contents to disk.
+pub fn sync_file(path: &Path) -> Result<()> {
+    File::open(path)?.sync_all()?;
+    Ok(())
+}
+
+/// Renames finished files to their published names. Fails before renaming
+/// anything if a published file already exists, rather than replacing it.
+pub fn publish(files: &[(PathBuf, PathBuf)]) -> Result<()> {
+    if let Some((_, target)) = files.iter().find(|(_, target)| target.exists()) {
+        return Err(IndexerError::StorageError(format!("Refusing to overwrite {}", target.display())).into());
+    }
+    for (source, target) in files {
+        std::fs::rename(source, target)?;
+    }
+    Ok(())
+}
+
+/// Makes renames and removals in `dir` durable.
+pub fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Deals with temporary files left in `data_dir` by a crash. The row groups
+/// a window's journal vouches for are rewritten into published files and
+/// their blocks marked indexed; a window that cannot be recovered in full,
+/// and anything else, is moved to `quarantine/`.
+/// Only row groups that were synced to disk before a journal line was
+/// written are trusted. The parquet writer holds back up to 8 KiB of each
+/// file until its next write, so the newest flush can be lost; its blocks
+/// are picked up as gaps.
+pub fn recover_temp_files(data_dir: &Path, indexed: &IndexedBlocks) -> Result<()> {
+    let mut temp_files = BTreeSet::new();
+    let mut journals = Vec::new();
+    for entry in std::fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.ends_with(TEMP_SUFFIX) {
+            temp_files.insert(name.to_string());
+        } else if name.ends_with(PARTIAL_SUFFIX) {
+            // Left by a recovery that crashed; it is rewritten from the journal.
+            std::fs::remove_file(&path)?;
+        } else if name.ends_with(JOURNAL_SUFFIX) {
+            journals.push(path);
+        }
+    }
+    journals.sort();
+
+    for journal in journals {
+        let checkpoints = read_journal(&journal)?;
+        let names: BTreeSet<String> = checkpoints.iter()
+            .flat_map(|checkpoint| checkpoint.files.iter().map(|file| file.name.clone()))
+            .collect();
+
+        match recover_window(data_dir, &checkpoints, &names, indexed) {
+            Ok(blocks) => info!(
+                event = "window_recovered",
+                message = "Recovered files left open by an earlier run",
+                journal = %journal.display(),
+                first_block = blocks.first(),
+                last_block = blocks.last()
+            ),
+            Err(e) => {
+                warn!(
+                    event = "window_recovery_failed",
+                    message = "Could not recover files left open by an earlier run",
+                    journal = %journal.display(),
+                    error = %e
+                );
+                for name in &names {
+                    if data_dir.join(name).exists() {
+                        quarantine(data_dir, name)?;
+                    }
+                }
+            }
+        }
+
+        for name in &names {
+            temp_files.remove(name);
+        }
+        std::fs::remove_file(&journal)?;
+    }
+
+    for name in &temp_files {
+        if data_dir.join(name).exists() {
+            quarantine(data_dir, name)?;
+        }
+    }
+    sync_dir(data_dir)
+}
+
+/// Lines of a journal up to the first one cut short by a crash.
+fn read_journal(path: &Path) -> Result<Vec<Checkpoint>> {
+    let mut checkpoints = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        match serde_json::from_str(&line?) {
+            Ok(checkpoint) => checkpoints.push(checkpoint),
+            Err(_) => break,
+        }
+    }
+    Ok(checkpoints)
+}
+
+/// Publishes what can be trusted of one window's files and returns the
+/// blocks they hold.
+fn recover_window(
+    data_dir: &Path,
+    checkpoints: &[Checkpoint],
+    names: &BTreeSet<String>,
+    indexed: &IndexedBlocks,
+) -> Result<RangeSet> {
+    let lengths: HashMap<&str, u64> = names.iter()
+        .filter_map(|name| Some((name.as_str(), std::fs::metadata(data_dir.join(name)).ok()?.len())))
+        .collect();
+    // A file that already has its footer was closed, so every flush made it
+    // to disk and the crash hit while files were being renamed.
+    let closed = lengths.is_empty() || lengths.keys().any(|name| has_footer(&data_dir.join(name)));
+
+    let mut synced: HashMap<&str, u64> = HashMap::new();
+    for file in checkpoints.iter().flat_map(|checkpoint| &checkpoint.files) {
+        let length = synced.entry(file.name.as_str()).or_default();
+        *length = (*length).max(file.synced);
+    }
+    let complete = if closed {
+        checkpoints.len()
+    } else {
+        checkpoints.iter()
+            .take_while(|checkpoint| {
+                checkpoint.files.iter().all(|file| {
+                    let name = file.name.as_str();
+                    synced.get(name).is_some_and(|&len| len >= file.bytes)
+                        && lengths.get(name).is_some_and(|&len| len >= file.bytes)
+                })
+            })
+            .count()
+    };
+    let checkpoints = &checkpoints[..complete];
+
+    let mut blocks = RangeSet::default();
+    for checkpoint in checkpoints {
+        for &(start, end) in &checkpoint.blocks {
+            blocks.insert_range(start, end);
+        }
+    }
+    if blocks.is_empty() {
+        return Err(anyhow!("No complete flush recorded"));
+    }
+
+    // Every file is rewritten before any is published, so a failure leaves
+    // the whole window to be quarantined rather than half of it published.
+    let mut renames = Vec::new();
+    // Temporary files whose rows end up in a rewritten or published file.
+    let mut replaced = Vec::new();
+    let mut unused = Vec::new();
+    let mut prepared = || -> Result<()> {
+        for name in lengths.keys() {
+            let stem = name.strip_suffix(TEMP_SUFFIX)
+                .ok_or_else(|| anyhow!("Unexpected temporary file name {}", name))?;
+            let source = data_dir.join(name);
+            let target = data_dir.join(format!("{}.parquet", stem));
+            let entries: Vec<&CheckpointFile> = checkpoints.iter()
+                .flat_map(|checkpoint| &checkpoint.files)
+                .filter(|file| file.name == *name)
+                .collect();
+
+            if target.exists() {
+                // Published by a recovery that crashed before removing its
+                // sources. Names are reserved per window, so these are its rows.
+                replaced.push(source);
+            } else if closed && has_footer(&source) {
+                renames.push((source, target));
+            } else if entries.is_empty() {
+                unused.push(name);
+            } else {
+                let partial = data_dir.join(format!("{}{}", stem, PARTIAL_SUFFIX));
+                rewrite(&source, &partial, &entries)?;
+                replaced.push(source);
+                renames.push((partial, target));
+            }
+        }
+        Ok(())
+    };
+    if let Err(e) = prepared().and_then(|()| publish(&renames)) {
+        for stem in lengths.keys().filter_map(|name| name.strip_suffix(TEMP_SUFFIX)) {
+            let _ = std::fs::remove_file(data_dir.join(format!("{}{}", stem, PARTIAL_SUFFIX)));
+        }
+        return Err(e);
+    }
+
+    for source in &replaced {
+        std::fs::remove_file(source)?;
+    }
+    for name in unused {
+        quarantine(data_dir, name)?;
+    }
+    counter!("storage_files_recovered_total").increment(renames.len() as u64);
+
+    sync_dir(data_dir)?;
+    indexed.mark_published(&blocks)?;
+    Ok(blocks)
+}
+
+/// Copies the row groups described by `entries` out of an unfinished file
+/// into a complete, synced one at `target`.
+fn rewrite(source: &Path, target: &Path, entries: &[&CheckpointFile]) -> Result<()> {
+    let mut file_metadata = None;
+    let mut row_groups = Vec::new();
+    for entry in entries {
+        let metadata = ParquetMetaDataReader::decode_metadata(&hex::decode(&entry.footer)?)?;
+        file_metadata.get_or_insert_with(|| metadata.file_metadata().clone());
+        row_groups.extend(metadata.row_groups().iter().cloned());
+    }
+    let file_metadata = file_metadata.ok_or_else(|| anyhow!("No row groups to recover"))?;
+    let metadata = ArrowReaderMetadata::try_new(
+        Arc::new(ParquetMetaData::new(file_metadata, row_groups)),
+        Default::default(),
+    )?;
+    let schema = metadata.schema().clone();
+    let reader = ParquetRecordBatchReaderBuilder::new_with_metadata(File::open(source)?, metadata).build()?;
+
+    let props = WriterProperties::builder()
+        .set_compression(parquet::basic::Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(target)?, schema, Some(props))?;
+    for batch in reader {
+        writer.write(&batch?)?;
+    }
+    writer.close()?;
+    sync_file(target)?;
+    Ok(())
+}
+
+fn has_footer(path: &Path) -> bool {
+    File::open(path).ok().and_then(|file| SerializedFileReader::new(file).ok()).is_some()
+}
+
+fn quarantine(data_dir: &Path, name: &str) -> Result<()> {
+    let dir = data_dir.join(QUARANTINE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::rename(data_dir.join(name), dir.join(name))?;
+    counter!("storage_files_quarantined_total").increment(1);
+    warn!(
+        event = "temp_file_quarantined",
+        message = "Moved unrecoverable temporary file aside",
+        file = name,
+        quarantine_dir = %dir.display()
+    );
+    Ok(())
+}
+
+fn file_name(path: &Path) -> Result<&str> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Unexpected file name {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{StringArray, UInt64Array},
+        datatypes::{DataType, Field},
+        record_batch::RecordBatch,
+    };
+    use parquet::file::reader::FileReader;
+
+    const INDEXED_BLOCKS_NAME: &str = "indexed_blocks.json";
+    /// Key of the window the tests write, blocks 10 to 19.
+    const KEY: &str = "000000010_000000019";
+
+    /// Rows written per block, each large enough that a flush spills out of
+    /// the parquet writer's buffer.
+    const ROWS_PER_BLOCK: u64 = 50;
+
+    /// A window being written the way `StorageManager` does, one row group
+    /// and journal line per flush.
+    struct Window {
+        dir: PathBuf,
+        path: PathBuf,
+        schema: Arc<Schema>,
+        writer: ArrowWriter<File>,
+        journal: CheckpointJournal,
+    }
+
+    impl Window {
+        fn open(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("checkpoints_{}_{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("number", DataType::UInt64, false),
+                Field::new("payload", DataType::Utf8, false),
+            ]));
+            let path = dir.join(format!("blocks_{}{}", KEY, TEMP_SUFFIX));
+            let writer = ArrowWriter::try_new(File::create(&path).unwrap(), schema.clone(), None).unwrap();
+            let journal = CheckpointJournal::create(&dir, KEY).unwrap().unwrap();
+            Self { dir, path, schema, writer, journal }
+        }
+
+        fn flush(&mut self, first: u64, last: u64) {
+            let numbers: Vec<u64> = (first..=last)
+                .flat_map(|number| std::iter::repeat_n(number, ROWS_PER_BLOCK as usize))
+                .collect();
+            let payloads: Vec<String> = (0..numbers.len()).map(|row| format!("{:0>200}", row)).collect();
+            let batch = RecordBatch::try_new(self.schema.clone(), vec![
+                Arc::new(UInt64Array::from(numbers)),
+                Arc::new(StringArray::from(payloads)),
+            ])
+            .unwrap();
+            let recorded = self.writer.flushed_row_groups().len();
+            self.writer.write(&batch).unwrap();
+            self.writer.flush().unwrap();
+
+            let mut blocks = RangeSet::default();
+            blocks.insert_range(first, last);
+            let files = CheckpointFile::new(&self.path, &self.writer, recorded, &self.schema).unwrap();
+            self.journal.append(&blocks, files.into_iter().collect()).unwrap();
+        }
+
+        /// Stops writing without a footer. Dropping the writer still empties
+        /// its buffer into the file, as a killed process would not.
+        fn crash(self) -> (PathBuf, PathBuf) {
+            drop(self.writer);
+            (self.dir, self.path)
+        }
+    }
+
+    fn journal_bytes(dir: &Path) -> Vec<(u64, u64)> {
+        read_journal(&dir.join(format!("window_{}{}", KEY, JOURNAL_SUFFIX))).unwrap()
+            .iter()
+            .map(|checkpoint| (checkpoint.files[0].bytes, checkpoint.files[0].synced))
+            .collect()
+    }
+
+    fn numbers(path: &Path) -> Vec<u64> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+        let mut numbers: Vec<u64> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                batch.column(0).as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec()
+            })
+            .collect();
+        numbers.dedup();
+        numbers
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name != INDEXED_BLOCKS_NAME)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn recovers_the_synced_flushes_of_a_crashed_window() {
+        let mut window = Window::open("synced");
+        window.flush(10, 11);
+        window.flush(12, 13);
+        window.flush(14, 15);
+        let (dir, _) = window.crash();
+        // The last flush was still partly in the writer's buffer when it was
+        // journaled, so a power loss could have taken it.
+        let journaled = journal_bytes(&dir);
+        assert!(journaled[2].1 < journaled[2].0);
+        assert!(journaled[2].1 >= journaled[1].0);
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        recover_temp_files(&dir, &indexed).unwrap();
+
+        assert_eq!(names(&dir), vec!["blocks_000000010_000000019.parquet"]);
+        assert_eq!(numbers(&dir.join("blocks_000000010_000000019.parquet")), vec![10, 11, 12, 13]);
+        assert_eq!(indexed.handled().iter().collect::<Vec<_>>(), vec![(10, 13)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_only_what_a_truncated_temp_file_still_holds() {
+        let mut window = Window::open("truncated");
+        window.flush(10, 11);
+        window.flush(12, 13);
+        window.flush(14, 15);
+        let (dir, path) = window.crash();
+        let journaled = journal_bytes(&dir);
+        // Cut into the second row group.
+        File::options().write(true).open(&path).unwrap().set_len(journaled[1].0 - 1).unwrap();
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        recover_temp_files(&dir, &indexed).unwrap();
+
+        assert_eq!(names(&dir), vec!["blocks_000000010_000000019.parquet"]);
+        assert_eq!(numbers(&dir.join("blocks_000000010_000000019.parquet")), vec![10, 11]);
+        assert_eq!(indexed.handled().iter().collect::<Vec<_>>(), vec![(10, 11)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_again_after_a_crash_between_rewrite_and_publish() {
+        let mut window = Window::open("republish");
+        window.flush(10, 11);
+        window.flush(12, 13);
+        window.flush(14, 15);
+        let (dir, _) = window.crash();
+        // An earlier recovery died while writing the rewritten file.
+        let partial = dir.join(format!("blocks_{}{}", KEY, PARTIAL_SUFFIX));
+        std::fs::write(&partial, b"PAR1 cut short").unwrap();
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        recover_temp_files(&dir, &indexed).unwrap();
+
+        assert_eq!(names(&dir), vec!["blocks_000000010_000000019.parquet"]);
+        assert_eq!(numbers(&dir.join("blocks_000000010_000000019.parquet")), vec![10, 11, 12, 13]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_again_after_a_crash_between_publish_and_cleanup() {
+        let mut window = Window::open("cleanup");
+        window.flush(10, 11);
+        window.flush(12, 13);
+        window.flush(14, 15);
+        let (dir, path) = window.crash();
+        let journal = dir.join(format!("window_{}{}", KEY, JOURNAL_SUFFIX));
+        let kept = (std::fs::read(&path).unwrap(), std::fs::read(&journal).unwrap());
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        recover_temp_files(&dir, &indexed).unwrap();
+        // An earlier recovery died after publishing, before removing these.
+        std::fs::write(&path, &kept.0).unwrap();
+        std::fs::write(&journal, &kept.1).unwrap();
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        recover_temp_files(&dir, &indexed).unwrap();
+
+        assert_eq!(names(&dir), vec!["blocks_000000010_000000019.parquet"]);
+        assert_eq!(numbers(&dir.join("blocks_000000010_000000019.parquet")), vec![10, 11, 12, 13]);
+        assert_eq!(indexed.handled().iter().collect::<Vec<_>>(), vec![(10, 13)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quarantines_what_cannot_be_recovered() {
+        let mut window = Window::open("quarantine");
+        window.flush(10, 11);
+        let (dir, path) = window.crash();
+        // Nothing the journal vouches for is left, and another file has no
+        // journal at all.
+        File::options().write(true).open(&path).unwrap().set_len(0).unwrap();
+        std::fs::write(dir.join(format!("logs_000000050_000000059{}", TEMP_SUFFIX)), b"stray").unwrap();
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        recover_temp_files(&dir, &indexed).unwrap();
+
+        assert_eq!(names(&dir), vec![QUARANTINE_DIR]);
+        assert_eq!(names(&dir.join(QUARANTINE_DIR)), vec![
+            format!("blocks_{}{}", KEY, TEMP_SUFFIX),
+            format!("logs_000000050_000000059{}", TEMP_SUFFIX),
+        ]);
+        assert!(indexed.handled().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rewrite_keeps_only_the_journaled_row_groups() {
+        let mut window = Window::open("rewrite");
+        window.flush(10, 11);
+        window.flush(12, 13);
+        window.flush(14, 15);
+        let (dir, path) = window.crash();
+        let checkpoints = read_journal(&dir.join(format!("window_{}{}", KEY, JOURNAL_SUFFIX))).unwrap();
+        let entries: Vec<&CheckpointFile> = [&checkpoints[0], &checkpoints[2]].iter()
+            .flat_map(|checkpoint| &checkpoint.files)
+            .collect();
+
+        let target = dir.join("rewritten.parquet");
+        rewrite(&path, &target, &entries).unwrap();
+
+        assert_eq!(numbers(&target), vec![10, 11, 14, 15]);
+        let metadata = SerializedFileReader::new(File::open(&target).unwrap()).unwrap();
+        assert_eq!(metadata.metadata().file_metadata().num_rows(), 4 * ROWS_PER_BLOCK as i64);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use crate::models::Block;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// A contract creation found in a receipt or trace, before its code has been
+/// fetched.
+#[derive(Debug, Clone)]
+pub struct Creation {
+    pub transaction_hash: String,
+    pub creator: String,
+    pub address: String,
+    pub internal: bool,
+}
+
+/// Deployments by the block's own transactions, from receipts that carry a
+/// `contractAddress`. Failed deployments are skipped.
+pub fn receipt_creations(block: &Block, receipts: &[Value]) -> Vec<Creation> {
+    let kept = transaction_hashes(block);
+
+    receipts.iter()
+        .filter(|receipt| receipt.get("status").and_then(Value::as_str) != Some("0x0"))
+        .filter_map(|receipt| {
+            let transaction_hash = receipt.get("transactionHash")?.as_str()?.to_lowercase();
+            if !kept.contains(transaction_hash.as_str()) {
+                return None;
+            }
+            Some(Creation {
+                creator: receipt.get("from")?.as_str()?.to_lowercase(),
+                address: receipt.get("contractAddress")?.as_str()?.to_lowercase(),
+                transaction_hash,
+                internal: false,
+            })
+        })
+        .collect()
+}
+
+/// Every successful `create` in a `trace_block` result, including those made
+/// by contracts during a call.
+pub fn trace_creations(block: &Block, traces: &[Value]) -> Vec<Creation> {
+    let kept = transaction_hashes(block);
+
+    traces.iter()
+        .filter(|trace| trace.get("type").and_then(Value::as_str) == Some("create"))
+        .filter(|trace| trace.get("error").is_none_or(Value::is_null))
+        .filter_map(|trace| {
+            let transaction_hash = trace.get("transactionHash")?.as_str()?.to_lowercase();
+            if !kept.contains(transaction_hash.as_str()) {
+                return None;
+            }
+            let internal = trace.get("traceAddress")
+                .and_then(Value::as_array)
+                .is_some_and(|path| !path.is_empty());
+            Some(Creation {
+                creator: trace.get("action")?.get("from")?.as_str()?.to_lowercase(),
+                address: trace.get("result")?.get("address")?.as_str()?.to_lowercase(),
+                transaction_hash,
+                internal,
+            })
+        })
+        .collect()
+}
+
+/// Hashes of the transactions left after filtering.
+fn transaction_hashes(block: &Block) -> HashSet<&str> {
+    block.transactions.iter().map(|tx| tx.hash.as_str()).collect()
+}
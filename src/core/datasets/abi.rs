@@ -0,0 +1,135 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::core::abi::{AbiTable, TableKind};
+use crate::models::{Block, DecodedValue};
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, ArrayRef, BooleanBuilder, Int64Builder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Builder for one ABI parameter column.
+enum ValueColumn {
+    UInt(UInt64Builder),
+    Int(Int64Builder),
+    Bool(BooleanBuilder),
+    Text(StringBuilder),
+}
+
+impl ValueColumn {
+    fn new(data_type: &DataType, capacity: usize) -> Self {
+        match data_type {
+            DataType::UInt64 => Self::UInt(UInt64Builder::with_capacity(capacity)),
+            DataType::Int64 => Self::Int(Int64Builder::with_capacity(capacity)),
+            DataType::Boolean => Self::Bool(BooleanBuilder::with_capacity(capacity)),
+            _ => Self::Text(StringBuilder::with_capacity(capacity, capacity * 32)),
+        }
+    }
+
+    fn append(&mut self, value: Option<&DecodedValue>) {
+        match (self, value) {
+            (Self::UInt(builder), Some(DecodedValue::UInt(v))) => builder.append_value(*v),
+            (Self::Int(builder), Some(DecodedValue::Int(v))) => builder.append_value(*v),
+            (Self::Bool(builder), Some(DecodedValue::Bool(v))) => builder.append_value(*v),
+            (Self::Text(builder), Some(DecodedValue::Text(v))) => builder.append_value(v),
+            (Self::UInt(builder), _) => builder.append_null(),
+            (Self::Int(builder), _) => builder.append_null(),
+            (Self::Bool(builder), _) => builder.append_null(),
+            (Self::Text(builder), _) => builder.append_null(),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::UInt(builder) => Arc::new(builder.finish()),
+            Self::Int(builder) => Arc::new(builder.finish()),
+            Self::Bool(builder) => Arc::new(builder.finish()),
+            Self::Text(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Events or calls of one contract item, decoded with a user-supplied ABI.
+pub struct AbiDataset {
+    table: AbiTable,
+    types: ColumnTypes,
+}
+
+impl AbiDataset {
+    pub fn new(table: AbiTable, types: ColumnTypes) -> Self {
+        Self { table, types }
+    }
+}
+
+impl Dataset for AbiDataset {
+    fn name(&self) -> &str {
+        &self.table.name
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.table.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let rows: Vec<_> = blocks.iter()
+            .flat_map(|block| &block.decoded)
+            .filter(|row| row.table == self.table.name)
+            .collect();
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let len = rows.len();
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut log_index_builder = UInt32Builder::with_capacity(len);
+        let mut address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut from_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut value_builder = ColumnBuilder::new(ColumnKind::Wei, self.types, len);
+
+        let fixed_columns = match self.table.kind {
+            TableKind::Event => 4,
+            TableKind::Call => 5,
+        };
+        let mut value_columns: Vec<ValueColumn> = self.table.schema.fields()[fixed_columns..]
+            .iter()
+            .map(|field| ValueColumn::new(field.data_type(), len))
+            .collect();
+
+        for row in rows {
+            block_number_builder.append_value(row.block_number);
+            tx_hash_builder.append(&row.transaction_hash)?;
+            log_index_builder.append_value(row.log_index.unwrap_or_default());
+            address_builder.append(&row.address)?;
+            // Only calls have a sender and value; event tables drop these columns.
+            if self.table.kind == TableKind::Call {
+                from_builder.append(row.from.as_deref().unwrap_or_default())?;
+                value_builder.append(row.value.as_deref().unwrap_or_default())?;
+            }
+            for (i, column) in value_columns.iter_mut().enumerate() {
+                column.append(row.values.get(i));
+            }
+        }
+
+        let mut columns: Vec<ArrayRef> = match self.table.kind {
+            TableKind::Event => vec![
+                Arc::new(block_number_builder.finish()),
+                tx_hash_builder.finish(),
+                Arc::new(log_index_builder.finish()),
+                address_builder.finish(),
+            ],
+            TableKind::Call => vec![
+                Arc::new(block_number_builder.finish()),
+                tx_hash_builder.finish(),
+                address_builder.finish(),
+                from_builder.finish(),
+                value_builder.finish(),
+            ],
+        };
+        columns.extend(value_columns.iter_mut().map(ValueColumn::finish));
+
+        Ok(Some(RecordBatch::try_new(self.table.schema.clone(), columns)?))
+    }
+}
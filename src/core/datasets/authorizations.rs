@@ -0,0 +1,83 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// EIP-7702 authorization tuples, one row per tuple.
+pub struct AuthorizationsDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl AuthorizationsDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("tx_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("authorization_index", DataType::UInt32, false),
+            // Decimal string, as chain ids can use all 256 bits.
+            Field::new("chain_id", DataType::Utf8, false),
+            Field::new("address", ColumnKind::Address.data_type(types), false),
+            Field::new("nonce", DataType::UInt64, false),
+            Field::new("signer", ColumnKind::Address.data_type(types), true),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for AuthorizationsDataset {
+    fn name(&self) -> &str {
+        "authorizations"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.authorizations.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut index_builder = UInt32Builder::with_capacity(len);
+        let mut chain_id_builder = StringBuilder::with_capacity(len, len * 8);
+        let mut address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut nonce_builder = UInt64Builder::with_capacity(len);
+        let mut signer_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+
+        for auth in blocks.iter().flat_map(|block| &block.authorizations) {
+            block_number_builder.append_value(auth.block_number);
+            tx_hash_builder.append(&auth.tx_hash)?;
+            index_builder.append_value(auth.index);
+            chain_id_builder.append_value(&auth.chain_id);
+            address_builder.append(&auth.address)?;
+            nonce_builder.append_value(auth.nonce);
+            signer_builder.append_option(auth.signer.as_deref())?;
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(block_number_builder.finish()),
+                tx_hash_builder.finish(),
+                Arc::new(index_builder.finish()),
+                Arc::new(chain_id_builder.finish()),
+                address_builder.finish(),
+                Arc::new(nonce_builder.finish()),
+                signer_builder.finish(),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
@@ -0,0 +1,134 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{UInt64Builder, StringBuilder, ListBuilder, StructBuilder, ArrayBuilder},
+    datatypes::{Schema, Field, DataType, Fields},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+pub struct BlocksDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl BlocksDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("number", DataType::UInt64, false),
+            Field::new("hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("parent_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("timestamp", ColumnKind::Timestamp.data_type(types), false),
+            Field::new("transactions", DataType::List(Arc::new(Field::new(
+                "transaction",
+                DataType::Struct(Self::transaction_fields(types)),
+                false,
+            ))), false),
+        ]));
+
+        Self { schema, types }
+    }
+
+    fn transaction_fields(types: ColumnTypes) -> Fields {
+        Fields::from(vec![
+            Field::new("hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("from", ColumnKind::Address.data_type(types), false),
+            Field::new("to", ColumnKind::Address.data_type(types), true),
+            Field::new("value", ColumnKind::Wei.data_type(types), false),
+            Field::new("input", DataType::Utf8, false),
+            Field::new("signature", DataType::Utf8, true),
+        ])
+    }
+}
+
+impl Dataset for BlocksDataset {
+    fn name(&self) -> &str {
+        "blocks"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let data_len: usize = blocks.iter()
+            .map(|block| block.transactions.len())
+            .sum();
+
+        let mut number_builder = UInt64Builder::with_capacity(blocks.len());
+        let mut hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, blocks.len());
+        let mut parent_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, blocks.len());
+        let mut timestamp_builder = ColumnBuilder::new(ColumnKind::Timestamp, self.types, blocks.len());
+
+        // Create builders for the transaction struct
+        let tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, data_len);
+        let tx_from_builder = ColumnBuilder::new(ColumnKind::Address, self.types, data_len);
+        let tx_to_builder = ColumnBuilder::new(ColumnKind::Address, self.types, data_len);
+        let tx_value_builder = ColumnBuilder::new(ColumnKind::Wei, self.types, data_len);
+        let tx_input_builder = StringBuilder::with_capacity(data_len, data_len * 10);
+        let tx_signature_builder = StringBuilder::with_capacity(data_len, data_len * 32);
+
+        let tx_struct_builder = StructBuilder::new(
+            Self::transaction_fields(self.types),
+            vec![
+                Box::new(tx_hash_builder),
+                Box::new(tx_from_builder),
+                Box::new(tx_to_builder),
+                Box::new(tx_value_builder),
+                Box::new(tx_input_builder),
+                Box::new(tx_signature_builder),
+            ],
+        );
+
+        let mut tx_list_builder = ListBuilder::new(tx_struct_builder)
+            .with_field(Field::new("transaction", DataType::Struct(Self::transaction_fields(self.types)), false));
+
+        for block in blocks {
+            number_builder.append_value(block.number);
+            hash_builder.append(&block.hash)?;
+            parent_hash_builder.append(&block.parent_hash)?;
+            timestamp_builder.append_seconds(block.timestamp)?;
+
+            let tx_list_values = tx_list_builder.values();
+            if let Some(struct_builder) = tx_list_values.as_any_mut().downcast_mut::<StructBuilder>() {
+                for tx in &block.transactions {
+                    if let Some(builder) = struct_builder.field_builder::<ColumnBuilder>(0) {
+                        builder.append(&tx.hash)?;
+                    }
+                    if let Some(builder) = struct_builder.field_builder::<ColumnBuilder>(1) {
+                        builder.append(&tx.from)?;
+                    }
+                    if let Some(builder) = struct_builder.field_builder::<ColumnBuilder>(2) {
+                        builder.append_option(tx.to.as_deref())?;
+                    }
+                    if let Some(builder) = struct_builder.field_builder::<ColumnBuilder>(3) {
+                        builder.append(&tx.value)?;
+                    }
+                    if let Some(builder) = struct_builder.field_builder::<StringBuilder>(4) {
+                        builder.append_value(&tx.input);
+                    }
+                    if let Some(builder) = struct_builder.field_builder::<StringBuilder>(5) {
+                        builder.append_option(tx.signature.as_deref());
+                    }
+                    struct_builder.append(true);
+                }
+            }
+            tx_list_builder.append(true);
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(number_builder.finish()),
+                hash_builder.finish(),
+                parent_hash_builder.finish(),
+                timestamp_builder.finish(),
+                Arc::new(tx_list_builder.finish()),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
@@ -0,0 +1,231 @@
+use crate::config::ColumnTypes;
+use crate::utils::error::IndexerError;
+use anyhow::Result;
+use arrow::{
+    array::{
+        ArrayBuilder, ArrayRef, Decimal256Builder, FixedSizeBinaryBuilder, StringBuilder, TimestampSecondBuilder,
+        UInt64Builder,
+    },
+    datatypes::{i256, DataType, TimeUnit},
+};
+use std::any::Any;
+use web3::types::U256;
+
+/// Digits of a native wei column, the most `Decimal256` can hold.
+const WEI_PRECISION: u8 = 76;
+
+/// Values written as hex or decimal strings in the default schema and as
+/// native types with `column_types = "native"`.
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnKind {
+    /// `FixedSizeBinary(32)`.
+    Hash,
+    /// `FixedSizeBinary(20)`.
+    Address,
+    /// `Decimal256(76, 0)`, wide enough for any ether amount. A value of 77
+    /// digits is rejected rather than written out of range. Token amounts can
+    /// use all 256 bits, so they stay decimal strings.
+    Wei,
+    /// Unix seconds, `Timestamp(Second, UTC)`.
+    Timestamp,
+}
+
+impl ColumnKind {
+    pub fn data_type(self, types: ColumnTypes) -> DataType {
+        match (types, self) {
+            (ColumnTypes::Strings, Self::Timestamp) => DataType::UInt64,
+            (ColumnTypes::Strings, _) => DataType::Utf8,
+            (ColumnTypes::Native, Self::Hash) => DataType::FixedSizeBinary(32),
+            (ColumnTypes::Native, Self::Address) => DataType::FixedSizeBinary(20),
+            (ColumnTypes::Native, Self::Wei) => DataType::Decimal256(WEI_PRECISION, 0),
+            (ColumnTypes::Native, Self::Timestamp) => DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+        }
+    }
+}
+
+/// Builds a `ColumnKind` column from the model's strings and numbers. It is
+/// an `ArrayBuilder` so it can also build fields of a struct column.
+pub enum ColumnBuilder {
+    Text(StringBuilder),
+    Bytes(FixedSizeBinaryBuilder),
+    Decimal(Decimal256Builder),
+    Seconds(UInt64Builder),
+    Timestamp(TimestampSecondBuilder),
+}
+
+impl ColumnBuilder {
+    pub fn new(kind: ColumnKind, types: ColumnTypes, capacity: usize) -> Self {
+        match kind.data_type(types) {
+            DataType::FixedSizeBinary(width) => Self::Bytes(FixedSizeBinaryBuilder::with_capacity(capacity, width)),
+            data_type @ DataType::Decimal256(..) => {
+                Self::Decimal(Decimal256Builder::with_capacity(capacity).with_data_type(data_type))
+            }
+            data_type @ DataType::Timestamp(..) => {
+                Self::Timestamp(TimestampSecondBuilder::with_capacity(capacity).with_data_type(data_type))
+            }
+            DataType::UInt64 => Self::Seconds(UInt64Builder::with_capacity(capacity)),
+            _ => Self::Text(StringBuilder::with_capacity(capacity, capacity * 66)),
+        }
+    }
+
+    /// Appends a 0x-prefixed hash or address, or a decimal amount.
+    pub fn append(&mut self, value: &str) -> Result<()> {
+        match self {
+            Self::Text(builder) => builder.append_value(value),
+            Self::Bytes(builder) => {
+                let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|_| unfit(value))?;
+                builder.append_value(bytes).map_err(|_| unfit(value))?;
+            }
+            Self::Decimal(builder) => {
+                let wei = U256::from_dec_str(value).map_err(|_| unfit(value))?;
+                if wei > U256::exp10(WEI_PRECISION as usize) - 1 {
+                    return Err(unfit(value));
+                }
+                let mut bytes = [0u8; 32];
+                wei.to_big_endian(&mut bytes);
+                builder.append_value(i256::from_be_bytes(bytes));
+            }
+            Self::Seconds(_) | Self::Timestamp(_) => return Err(unfit(value)),
+        }
+        Ok(())
+    }
+
+    pub fn append_option(&mut self, value: Option<&str>) -> Result<()> {
+        match value {
+            Some(value) => self.append(value),
+            None => {
+                self.append_null();
+                Ok(())
+            }
+        }
+    }
+
+    pub fn append_seconds(&mut self, seconds: u64) -> Result<()> {
+        match self {
+            Self::Seconds(builder) => builder.append_value(seconds),
+            Self::Timestamp(builder) => builder.append_value(seconds as i64),
+            _ => return Err(unfit(&seconds.to_string())),
+        }
+        Ok(())
+    }
+
+    pub fn append_null(&mut self) {
+        match self {
+            Self::Text(builder) => builder.append_null(),
+            Self::Bytes(builder) => builder.append_null(),
+            Self::Decimal(builder) => builder.append_null(),
+            Self::Seconds(builder) => builder.append_null(),
+            Self::Timestamp(builder) => builder.append_null(),
+        }
+    }
+
+    fn inner(&self) -> &dyn ArrayBuilder {
+        match self {
+            Self::Text(builder) => builder,
+            Self::Bytes(builder) => builder,
+            Self::Decimal(builder) => builder,
+            Self::Seconds(builder) => builder,
+            Self::Timestamp(builder) => builder,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn ArrayBuilder {
+        match self {
+            Self::Text(builder) => builder,
+            Self::Bytes(builder) => builder,
+            Self::Decimal(builder) => builder,
+            Self::Seconds(builder) => builder,
+            Self::Timestamp(builder) => builder,
+        }
+    }
+}
+
+impl ArrayBuilder for ColumnBuilder {
+    fn len(&self) -> usize {
+        self.inner().len()
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        self.inner_mut().finish()
+    }
+
+    fn finish_cloned(&self) -> ArrayRef {
+        self.inner().finish_cloned()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_box_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+fn unfit(value: &str) -> anyhow::Error {
+    IndexerError::StorageError(format!("Value {} does not fit its native column type", value)).into()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{Array, Decimal256Array},
+        datatypes::{Field, Schema},
+        record_batch::RecordBatch,
+    };
+    use bytes::Bytes;
+    use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter};
+    use std::sync::Arc;
+
+    const LARGEST_WEI: &str = "9999999999999999999999999999999999999999999999999999999999999999999999999999";
+
+    #[test]
+    fn native_wei_round_trips_through_parquet() {
+        let mut builder = ColumnBuilder::new(ColumnKind::Wei, ColumnTypes::Native, 4);
+        for value in ["0", "1000000000000000000", LARGEST_WEI] {
+            builder.append(value).unwrap();
+        }
+        builder.append_null();
+
+        let schema = Arc::new(Schema::new(vec![Field::new("value", ColumnKind::Wei.data_type(ColumnTypes::Native), true)]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![builder.finish()]).unwrap();
+        let mut file = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut file, schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file)).unwrap().build().unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch.schema().field(0).data_type(), &DataType::Decimal256(76, 0));
+        let values = batch.column(0).as_any().downcast_ref::<Decimal256Array>().unwrap();
+        let written: Vec<_> = values.iter().map(|value| value.map(|value| value.to_string())).collect();
+        assert_eq!(written, vec![
+            Some("0".to_string()),
+            Some("1000000000000000000".to_string()),
+            Some(LARGEST_WEI.to_string()),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn native_wei_rejects_values_beyond_76_digits() {
+        let mut builder = ColumnBuilder::new(ColumnKind::Wei, ColumnTypes::Native, 2);
+        assert!(builder.append(&format!("1{}", "0".repeat(76))).is_err());
+        assert!(builder.append(&U256::MAX.to_string()).is_err());
+        assert!(builder.append("-1").is_err());
+        assert_eq!(builder.len(), 0);
+    }
+
+    #[test]
+    fn string_wei_keeps_any_uint256() {
+        let mut builder = ColumnBuilder::new(ColumnKind::Wei, ColumnTypes::Strings, 1);
+        builder.append(&U256::MAX.to_string()).unwrap();
+        assert_eq!(builder.finish().data_type(), &DataType::Utf8);
+    }
+}
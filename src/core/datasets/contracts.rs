@@ -0,0 +1,83 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, BooleanBuilder, StringBuilder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Deployed contracts with their runtime bytecode. `code_hash` identifies
+/// identical deployments.
+pub struct ContractsDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl ContractsDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("transaction_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("creator", ColumnKind::Address.data_type(types), false),
+            Field::new("address", ColumnKind::Address.data_type(types), false),
+            Field::new("internal", DataType::Boolean, false),
+            Field::new("code", DataType::Utf8, false),
+            Field::new("code_hash", ColumnKind::Hash.data_type(types), false),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for ContractsDataset {
+    fn name(&self) -> &str {
+        "contracts"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.contracts.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut creator_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut internal_builder = BooleanBuilder::with_capacity(len);
+        let mut code_builder = StringBuilder::with_capacity(len, len * 1024);
+        let mut code_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+
+        for contract in blocks.iter().flat_map(|block| &block.contracts) {
+            block_number_builder.append_value(contract.block_number);
+            tx_hash_builder.append(&contract.transaction_hash)?;
+            creator_builder.append(&contract.creator)?;
+            address_builder.append(&contract.address)?;
+            internal_builder.append_value(contract.internal);
+            code_builder.append_value(&contract.code);
+            code_hash_builder.append(&contract.code_hash)?;
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(block_number_builder.finish()),
+                tx_hash_builder.finish(),
+                creator_builder.finish(),
+                address_builder.finish(),
+                Arc::new(internal_builder.finish()),
+                Arc::new(code_builder.finish()),
+                code_hash_builder.finish(),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
@@ -0,0 +1,91 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Event logs from receipts, one row per log. Topics are split into fixed
+/// columns since no log carries more than four.
+pub struct LogsDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl LogsDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("transaction_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("transaction_index", DataType::UInt32, false),
+            Field::new("log_index", DataType::UInt32, false),
+            Field::new("address", ColumnKind::Address.data_type(types), false),
+            Field::new("topic0", ColumnKind::Hash.data_type(types), true),
+            Field::new("topic1", ColumnKind::Hash.data_type(types), true),
+            Field::new("topic2", ColumnKind::Hash.data_type(types), true),
+            Field::new("topic3", ColumnKind::Hash.data_type(types), true),
+            Field::new("data", DataType::Utf8, false),
+            Field::new("event_signature", DataType::Utf8, true),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for LogsDataset {
+    fn name(&self) -> &str {
+        "logs"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.logs.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut tx_index_builder = UInt32Builder::with_capacity(len);
+        let mut log_index_builder = UInt32Builder::with_capacity(len);
+        let mut address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut topic_builders: Vec<ColumnBuilder> = (0..4)
+            .map(|_| ColumnBuilder::new(ColumnKind::Hash, self.types, len))
+            .collect();
+        let mut data_builder = StringBuilder::with_capacity(len, len * 66);
+        let mut signature_builder = StringBuilder::with_capacity(len, len * 32);
+
+        for log in blocks.iter().flat_map(|block| &block.logs) {
+            block_number_builder.append_value(log.block_number);
+            tx_hash_builder.append(&log.transaction_hash)?;
+            tx_index_builder.append_value(log.transaction_index);
+            log_index_builder.append_value(log.log_index);
+            address_builder.append(&log.address)?;
+            for (i, builder) in topic_builders.iter_mut().enumerate() {
+                builder.append_option(log.topics.get(i).map(String::as_str))?;
+            }
+            data_builder.append_value(&log.data);
+            signature_builder.append_option(log.signature.as_deref());
+        }
+
+        let mut columns: Vec<arrow::array::ArrayRef> = vec![
+            Arc::new(block_number_builder.finish()),
+            tx_hash_builder.finish(),
+            Arc::new(tx_index_builder.finish()),
+            Arc::new(log_index_builder.finish()),
+            address_builder.finish(),
+        ];
+        columns.extend(topic_builders.iter_mut().map(|builder| builder.finish()));
+        columns.push(Arc::new(data_builder.finish()));
+        columns.push(Arc::new(signature_builder.finish()));
+
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), columns)?))
+    }
+}
@@ -0,0 +1,42 @@
+mod abi;
+mod authorizations;
+mod blocks;
+mod columns;
+mod contracts;
+mod logs;
+mod pending_transactions;
+mod state_diffs;
+mod token_transfers;
+mod tokens;
+mod uncles;
+
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{datatypes::Schema, record_batch::RecordBatch};
+use std::sync::Arc;
+
+pub use abi::AbiDataset;
+pub use authorizations::AuthorizationsDataset;
+pub use blocks::BlocksDataset;
+pub use columns::{ColumnBuilder, ColumnKind};
+pub use contracts::ContractsDataset;
+pub use logs::LogsDataset;
+pub use pending_transactions::PendingTransactionsDataset;
+pub use state_diffs::StateDiffsDataset;
+pub use token_transfers::TokenTransfersDataset;
+pub use tokens::TokensDataset;
+pub use uncles::UnclesDataset;
+
+/// A parquet dataset derived from a batch of blocks.
+///
+/// Each dataset is written to its own `<name>_*.parquet` files by the
+/// `StorageManager`.
+pub trait Dataset: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn schema(&self) -> Arc<Schema>;
+
+    /// Builds the rows for `blocks`, or `None` if the batch has no rows for
+    /// this dataset.
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>>;
+}
@@ -0,0 +1,91 @@
+use super::{ColumnBuilder, ColumnKind};
+use crate::config::ColumnTypes;
+use crate::models::PendingTransaction;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Mempool transactions with the time they were first seen. Unlike the
+/// block datasets it is fed by the mempool recorder, not by `Block`s.
+pub struct PendingTransactionsDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl PendingTransactionsDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("first_seen_ms", DataType::UInt64, false),
+            Field::new("from", ColumnKind::Address.data_type(types), true),
+            Field::new("to", ColumnKind::Address.data_type(types), true),
+            Field::new("nonce", DataType::UInt64, true),
+            Field::new("value", ColumnKind::Wei.data_type(types), true),
+            Field::new("gas", DataType::UInt64, true),
+            Field::new("gas_price", ColumnKind::Wei.data_type(types), true),
+            Field::new("max_fee_per_gas", ColumnKind::Wei.data_type(types), true),
+            Field::new("max_priority_fee_per_gas", ColumnKind::Wei.data_type(types), true),
+            Field::new("input", DataType::Utf8, true),
+        ]));
+
+        Self { schema, types }
+    }
+
+    pub fn name(&self) -> &str {
+        "pending_transactions"
+    }
+
+    pub fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    pub fn build_batch(&self, transactions: &[PendingTransaction]) -> Result<RecordBatch> {
+        let len = transactions.len();
+        let mut hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut first_seen_builder = UInt64Builder::with_capacity(len);
+        let mut from_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut to_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut nonce_builder = UInt64Builder::with_capacity(len);
+        let mut value_builder = ColumnBuilder::new(ColumnKind::Wei, self.types, len);
+        let mut gas_builder = UInt64Builder::with_capacity(len);
+        let mut gas_price_builder = ColumnBuilder::new(ColumnKind::Wei, self.types, len);
+        let mut max_fee_builder = ColumnBuilder::new(ColumnKind::Wei, self.types, len);
+        let mut max_priority_fee_builder = ColumnBuilder::new(ColumnKind::Wei, self.types, len);
+        let mut input_builder = StringBuilder::with_capacity(len, len * 10);
+
+        for tx in transactions {
+            hash_builder.append(&tx.hash)?;
+            first_seen_builder.append_value(tx.first_seen_ms);
+            from_builder.append_option(tx.from.as_deref())?;
+            to_builder.append_option(tx.to.as_deref())?;
+            nonce_builder.append_option(tx.nonce);
+            value_builder.append_option(tx.value.as_deref())?;
+            gas_builder.append_option(tx.gas);
+            gas_price_builder.append_option(tx.gas_price.as_deref())?;
+            max_fee_builder.append_option(tx.max_fee_per_gas.as_deref())?;
+            max_priority_fee_builder.append_option(tx.max_priority_fee_per_gas.as_deref())?;
+            input_builder.append_option(tx.input.as_ref());
+        }
+
+        Ok(RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                hash_builder.finish(),
+                Arc::new(first_seen_builder.finish()),
+                from_builder.finish(),
+                to_builder.finish(),
+                Arc::new(nonce_builder.finish()),
+                value_builder.finish(),
+                Arc::new(gas_builder.finish()),
+                gas_price_builder.finish(),
+                max_fee_builder.finish(),
+                max_priority_fee_builder.finish(),
+                Arc::new(input_builder.finish()),
+            ],
+        )?)
+    }
+}
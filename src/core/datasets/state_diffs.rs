@@ -0,0 +1,87 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Balance, nonce, code and storage changes, one row per changed field or
+/// storage slot of each account touched by a transaction.
+pub struct StateDiffsDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl StateDiffsDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("transaction_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("transaction_index", DataType::UInt32, false),
+            Field::new("address", ColumnKind::Address.data_type(types), false),
+            Field::new("field", DataType::Utf8, false),
+            Field::new("slot", ColumnKind::Hash.data_type(types), true),
+            Field::new("from", DataType::Utf8, true),
+            Field::new("to", DataType::Utf8, true),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for StateDiffsDataset {
+    fn name(&self) -> &str {
+        "state_diffs"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.state_diffs.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut tx_index_builder = UInt32Builder::with_capacity(len);
+        let mut address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut field_builder = StringBuilder::with_capacity(len, len * 7);
+        let mut slot_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut from_builder = StringBuilder::with_capacity(len, len * 66);
+        let mut to_builder = StringBuilder::with_capacity(len, len * 66);
+
+        for diff in blocks.iter().flat_map(|block| &block.state_diffs) {
+            block_number_builder.append_value(diff.block_number);
+            tx_hash_builder.append(&diff.transaction_hash)?;
+            tx_index_builder.append_value(diff.transaction_index);
+            address_builder.append(&diff.address)?;
+            field_builder.append_value(diff.field.as_str());
+            slot_builder.append_option(diff.slot.as_deref())?;
+            from_builder.append_option(diff.from.as_ref());
+            to_builder.append_option(diff.to.as_ref());
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(block_number_builder.finish()),
+                tx_hash_builder.finish(),
+                Arc::new(tx_index_builder.finish()),
+                address_builder.finish(),
+                Arc::new(field_builder.finish()),
+                slot_builder.finish(),
+                Arc::new(from_builder.finish()),
+                Arc::new(to_builder.finish()),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
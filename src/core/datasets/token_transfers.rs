@@ -0,0 +1,98 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// ERC-20, ERC-721 and ERC-1155 transfers decoded from logs.
+pub struct TokenTransfersDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl TokenTransfersDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("transaction_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("log_index", DataType::UInt32, false),
+            Field::new("batch_index", DataType::UInt32, true),
+            Field::new("token_address", ColumnKind::Address.data_type(types), false),
+            Field::new("standard", DataType::Utf8, false),
+            Field::new("operator", ColumnKind::Address.data_type(types), true),
+            Field::new("from", ColumnKind::Address.data_type(types), false),
+            Field::new("to", ColumnKind::Address.data_type(types), false),
+            Field::new("token_id", DataType::Utf8, true),
+            Field::new("amount", DataType::Utf8, false),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for TokenTransfersDataset {
+    fn name(&self) -> &str {
+        "token_transfers"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.token_transfers.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut tx_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut log_index_builder = UInt32Builder::with_capacity(len);
+        let mut batch_index_builder = UInt32Builder::with_capacity(len);
+        let mut token_address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut standard_builder = StringBuilder::with_capacity(len, len * 7);
+        let mut operator_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut from_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut to_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut token_id_builder = StringBuilder::with_capacity(len, len * 8);
+        let mut amount_builder = StringBuilder::with_capacity(len, len * 20);
+
+        for transfer in blocks.iter().flat_map(|block| &block.token_transfers) {
+            block_number_builder.append_value(transfer.block_number);
+            tx_hash_builder.append(&transfer.transaction_hash)?;
+            log_index_builder.append_value(transfer.log_index);
+            batch_index_builder.append_option(transfer.batch_index);
+            token_address_builder.append(&transfer.token_address)?;
+            standard_builder.append_value(transfer.standard.as_str());
+            operator_builder.append_option(transfer.operator.as_deref())?;
+            from_builder.append(&transfer.from)?;
+            to_builder.append(&transfer.to)?;
+            token_id_builder.append_option(transfer.token_id.as_ref());
+            amount_builder.append_value(&transfer.amount);
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(block_number_builder.finish()),
+                tx_hash_builder.finish(),
+                Arc::new(log_index_builder.finish()),
+                Arc::new(batch_index_builder.finish()),
+                token_address_builder.finish(),
+                Arc::new(standard_builder.finish()),
+                operator_builder.finish(),
+                from_builder.finish(),
+                to_builder.finish(),
+                Arc::new(token_id_builder.finish()),
+                Arc::new(amount_builder.finish()),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
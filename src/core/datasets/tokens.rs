@@ -0,0 +1,83 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt64Builder, UInt8Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Token contracts with their metadata, one row per token when it is first
+/// seen in a transfer.
+pub struct TokensDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl TokensDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("address", ColumnKind::Address.data_type(types), false),
+            Field::new("standard", DataType::Utf8, false),
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("symbol", DataType::Utf8, true),
+            Field::new("decimals", DataType::UInt8, true),
+            Field::new("total_supply", DataType::Utf8, true),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for TokensDataset {
+    fn name(&self) -> &str {
+        "tokens"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.tokens.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut address_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut standard_builder = StringBuilder::with_capacity(len, len * 7);
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut name_builder = StringBuilder::with_capacity(len, len * 16);
+        let mut symbol_builder = StringBuilder::with_capacity(len, len * 8);
+        let mut decimals_builder = UInt8Builder::with_capacity(len);
+        let mut total_supply_builder = StringBuilder::with_capacity(len, len * 20);
+
+        for token in blocks.iter().flat_map(|block| &block.tokens) {
+            address_builder.append(&token.address)?;
+            standard_builder.append_value(token.standard.as_str());
+            block_number_builder.append_value(token.block_number);
+            name_builder.append_option(token.name.as_ref());
+            symbol_builder.append_option(token.symbol.as_ref());
+            decimals_builder.append_option(token.decimals);
+            total_supply_builder.append_option(token.total_supply.as_ref());
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                address_builder.finish(),
+                Arc::new(standard_builder.finish()),
+                Arc::new(block_number_builder.finish()),
+                Arc::new(name_builder.finish()),
+                Arc::new(symbol_builder.finish()),
+                Arc::new(decimals_builder.finish()),
+                Arc::new(total_supply_builder.finish()),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
@@ -0,0 +1,94 @@
+use super::{ColumnBuilder, ColumnKind, Dataset};
+use crate::config::ColumnTypes;
+use crate::models::Block;
+use anyhow::Result;
+use arrow::{
+    array::{ArrayBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Uncle headers, one row per uncle with the number of the including block.
+pub struct UnclesDataset {
+    schema: Arc<Schema>,
+    types: ColumnTypes,
+}
+
+impl UnclesDataset {
+    pub fn new(types: ColumnTypes) -> Self {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("block_number", DataType::UInt64, false),
+            Field::new("uncle_index", DataType::UInt32, false),
+            Field::new("number", DataType::UInt64, false),
+            Field::new("hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("parent_hash", ColumnKind::Hash.data_type(types), false),
+            Field::new("miner", ColumnKind::Address.data_type(types), false),
+            Field::new("timestamp", ColumnKind::Timestamp.data_type(types), false),
+            Field::new("difficulty", DataType::Utf8, false),
+            Field::new("gas_limit", DataType::UInt64, false),
+            Field::new("gas_used", DataType::UInt64, false),
+        ]));
+
+        Self { schema, types }
+    }
+}
+
+impl Dataset for UnclesDataset {
+    fn name(&self) -> &str {
+        "uncles"
+    }
+
+    fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    fn build_batch(&self, blocks: &[Block]) -> Result<Option<RecordBatch>> {
+        let len: usize = blocks.iter().map(|block| block.uncles.len()).sum();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let mut block_number_builder = UInt64Builder::with_capacity(len);
+        let mut uncle_index_builder = UInt32Builder::with_capacity(len);
+        let mut number_builder = UInt64Builder::with_capacity(len);
+        let mut hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut parent_hash_builder = ColumnBuilder::new(ColumnKind::Hash, self.types, len);
+        let mut miner_builder = ColumnBuilder::new(ColumnKind::Address, self.types, len);
+        let mut timestamp_builder = ColumnBuilder::new(ColumnKind::Timestamp, self.types, len);
+        let mut difficulty_builder = StringBuilder::with_capacity(len, len * 16);
+        let mut gas_limit_builder = UInt64Builder::with_capacity(len);
+        let mut gas_used_builder = UInt64Builder::with_capacity(len);
+
+        for uncle in blocks.iter().flat_map(|block| &block.uncles) {
+            block_number_builder.append_value(uncle.block_number);
+            uncle_index_builder.append_value(uncle.uncle_index);
+            number_builder.append_value(uncle.number);
+            hash_builder.append(&uncle.hash)?;
+            parent_hash_builder.append(&uncle.parent_hash)?;
+            miner_builder.append(&uncle.miner)?;
+            timestamp_builder.append_seconds(uncle.timestamp)?;
+            difficulty_builder.append_value(&uncle.difficulty);
+            gas_limit_builder.append_value(uncle.gas_limit);
+            gas_used_builder.append_value(uncle.gas_used);
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(block_number_builder.finish()),
+                Arc::new(uncle_index_builder.finish()),
+                Arc::new(number_builder.finish()),
+                hash_builder.finish(),
+                parent_hash_builder.finish(),
+                miner_builder.finish(),
+                timestamp_builder.finish(),
+                Arc::new(difficulty_builder.finish()),
+                Arc::new(gas_limit_builder.finish()),
+                Arc::new(gas_used_builder.finish()),
+            ],
+        )?;
+
+        Ok(Some(batch))
+    }
+}
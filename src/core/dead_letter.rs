@@ -0,0 +1,72 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+#[derive(Serialize)]
+struct DeadLetter<'a> {
+    recorded_at: String,
+    block_number: Option<u64>,
+    source: &'a str,
+    error: String,
+    action: &'a str,
+    raw: &'a Value,
+}
+
+/// Append-only `dead_letter.jsonl` in `data_dir` holding blocks that could
+/// not be decoded, together with their raw JSON.
+pub struct DeadLetterQueue {
+    file: Mutex<File>,
+    /// Last entry written, so retrying the same block does not flood the file.
+    last_recorded: Mutex<Option<(Option<u64>, String)>>,
+}
+
+impl DeadLetterQueue {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(data_dir.join("dead_letter.jsonl"))?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+            last_recorded: Mutex::new(None),
+        })
+    }
+
+    pub fn record(
+        &self,
+        block_number: Option<u64>,
+        source: &str,
+        error: &dyn std::error::Error,
+        action: &str,
+        raw: &Value,
+    ) -> Result<()> {
+        let key = (block_number, source.to_string());
+        let mut last_recorded = self.last_recorded.lock().unwrap();
+        if last_recorded.as_ref() == Some(&key) {
+            return Ok(());
+        }
+
+        let mut line = serde_json::to_vec(&DeadLetter {
+            recorded_at: Utc::now().to_rfc3339(),
+            block_number,
+            source,
+            error: error.to_string(),
+            action,
+            raw,
+        })?;
+        line.push(b'\n');
+        self.file.lock().unwrap().write_all(&line)?;
+
+        *last_recorded = Some(key);
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use crate::core::authorization::decode_authorizations;
+use crate::models::{Block, Log, PendingTransaction, Transaction};
+use crate::utils::error::DecodeError;
+use serde::Deserialize;
+use serde_json::Value;
+use web3::types::U256;
+
+/// Decodes a raw `eth_getBlockByNumber` result (with full transactions)
+/// into our block model. Uncles are not part of the response and are left
+/// empty.
+pub fn decode_block(raw: &Value) -> Result<Block, DecodeError> {
+    let block = web3::types::Block::<web3::types::Transaction>::deserialize(raw)
+        .map_err(|e| DecodeError::Malformed(e.to_string()))?;
+
+    let number = block.number.ok_or(DecodeError::Pending("number"))?.as_u64();
+    let hash = block.hash.ok_or(DecodeError::Pending("hash"))?;
+
+    let mut authorizations = Vec::new();
+    if let Some(Value::Array(txs)) = raw.get("transactions") {
+        for tx in txs {
+            authorizations.extend(decode_authorizations(number, tx)?);
+        }
+    }
+
+    let transactions = block.transactions.into_iter()
+        .map(|tx| {
+            let from = tx.from.ok_or(DecodeError::MissingField("transaction.from"))?;
+            Ok(Transaction {
+                hash: format!("{:?}", tx.hash),
+                from: format!("{:?}", from),
+                to: tx.to.map(|addr| format!("{:?}", addr)),
+                value: tx.value.to_string(),
+                input: format!("0x{}", hex::encode(&tx.input.0)),
+                signature: None,
+            })
+        })
+        .collect::<Result<_, DecodeError>>()?;
+
+    Ok(Block {
+        number,
+        hash: format!("{:?}", hash),
+        parent_hash: format!("{:?}", block.parent_hash),
+        transactions,
+        timestamp: to_u64(block.timestamp, "timestamp")?,
+        authorizations,
+        uncles: Vec::new(),
+        logs: Vec::new(),
+        token_transfers: Vec::new(),
+        contracts: Vec::new(),
+        state_diffs: Vec::new(),
+        tokens: Vec::new(),
+        decoded: Vec::new(),
+    })
+}
+
+/// Decodes a full transaction object from a pending-transaction
+/// subscription or `eth_getTransactionByHash`.
+pub fn decode_pending_transaction(raw: &Value, first_seen_ms: u64) -> Result<PendingTransaction, DecodeError> {
+    let tx = web3::types::Transaction::deserialize(raw)
+        .map_err(|e| DecodeError::Malformed(e.to_string()))?;
+
+    Ok(PendingTransaction {
+        hash: format!("{:?}", tx.hash),
+        first_seen_ms,
+        from: tx.from.map(|from| format!("{:?}", from)),
+        to: tx.to.map(|to| format!("{:?}", to)),
+        nonce: Some(to_u64(tx.nonce, "transaction.nonce")?),
+        value: Some(tx.value.to_string()),
+        gas: Some(to_u64(tx.gas, "transaction.gas")?),
+        gas_price: tx.gas_price.map(|price| price.to_string()),
+        max_fee_per_gas: tx.max_fee_per_gas.map(|fee| fee.to_string()),
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas.map(|fee| fee.to_string()),
+        input: Some(format!("0x{}", hex::encode(&tx.input.0))),
+    })
+}
+
+/// Decodes the logs of raw receipts, as returned by `eth_getBlockReceipts`
+/// or collected from `eth_getTransactionReceipt`.
+pub fn decode_receipt_logs(block_number: u64, receipts: &[Value]) -> Result<Vec<Log>, DecodeError> {
+    let mut logs = Vec::new();
+
+    for receipt in receipts {
+        let Some(Value::Array(raw_logs)) = receipt.get("logs") else {
+            continue;
+        };
+        for raw_log in raw_logs {
+            let log = web3::types::Log::deserialize(raw_log)
+                .map_err(|e| DecodeError::InvalidField { field: "logs", reason: e.to_string() })?;
+            let transaction_hash = log.transaction_hash.ok_or(DecodeError::MissingField("log.transactionHash"))?;
+            let transaction_index = log.transaction_index.ok_or(DecodeError::MissingField("log.transactionIndex"))?;
+            let log_index = log.log_index.ok_or(DecodeError::MissingField("log.logIndex"))?;
+
+            logs.push(Log {
+                block_number,
+                transaction_hash: format!("{:?}", transaction_hash),
+                transaction_index: transaction_index.as_u32(),
+                log_index: to_u64(log_index, "log.logIndex")? as u32,
+                address: format!("{:?}", log.address),
+                topics: log.topics.iter().map(|topic| format!("{:?}", topic)).collect(),
+                data: format!("0x{}", hex::encode(&log.data.0)),
+                signature: None,
+            });
+        }
+    }
+
+    Ok(logs)
+}
+
+/// The block's 2048-bit logs bloom, if present.
+pub fn logs_bloom(raw: &Value) -> Option<Vec<u8>> {
+    let bloom = raw.get("logsBloom")?.as_str()?;
+    hex::decode(bloom.trim_start_matches("0x")).ok().filter(|bytes| bytes.len() == 256)
+}
+
+/// Number of uncles referenced by a raw block.
+pub fn uncle_count(raw: &Value) -> usize {
+    raw.get("uncles").and_then(Value::as_array).map_or(0, Vec::len)
+}
+
+/// Block number of a raw block, if it can be read at all.
+pub fn raw_block_number(raw: &Value) -> Option<u64> {
+    let number = raw.get("number")?.as_str()?;
+    u64::from_str_radix(number.trim_start_matches("0x"), 16).ok()
+}
+
+pub fn to_u64(value: U256, field: &'static str) -> Result<u64, DecodeError> {
+    u64::try_from(value).map_err(|_| DecodeError::Overflow(field))
+}
@@ -0,0 +1,105 @@
+use crate::config::FilterConfig;
+use crate::models::{Block, Transaction};
+use metrics::counter;
+use std::collections::HashSet;
+use web3::signing::keccak256;
+
+/// Applies the configured `FilterConfig` to decoded blocks.
+#[derive(Debug, Clone)]
+pub struct BlockFilter {
+    config: FilterConfig,
+    /// Raw bytes of the log emitters, for bloom lookups.
+    emitter_bytes: Vec<Vec<u8>>,
+}
+
+impl BlockFilter {
+    pub fn new(config: &FilterConfig) -> Self {
+        Self {
+            config: config.clone(),
+            emitter_bytes: config.log_emitters.iter()
+                .filter_map(|address| hex::decode(address.trim_start_matches("0x")).ok())
+                .collect(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.config.addresses.is_empty()
+            || self.config.contract_creations
+            || !self.config.selectors.is_empty()
+            || !self.config.log_emitters.is_empty()
+    }
+
+    fn matches_transaction(&self, tx: &Transaction) -> bool {
+        let config = &self.config;
+        config.addresses.contains(&tx.from)
+            || tx.to.as_ref().is_some_and(|to| config.addresses.contains(to))
+            || (config.contract_creations && tx.to.is_none())
+            || tx.input.get(..10).is_some_and(|selector| config.selectors.contains(selector))
+    }
+
+    /// Whether the block's receipts have to be fetched. `all_logs` asks for
+    /// every log; otherwise logs are only needed to match log emitters.
+    pub fn needs_receipts(&self, block: &Block, bloom: Option<&[u8]>, all_logs: bool) -> bool {
+        if block.transactions.is_empty() || (!all_logs && self.config.log_emitters.is_empty()) {
+            return false;
+        }
+        let Some(bloom) = bloom.filter(|_| self.config.use_bloom) else {
+            return true;
+        };
+        let needed = if bloom.iter().all(|&byte| byte == 0) {
+            false
+        } else if !self.is_active() {
+            true
+        } else {
+            // Logs of transactions kept by the other criteria are stored too.
+            (all_logs && block.transactions.iter().any(|tx| self.matches_transaction(tx)))
+                || self.emitter_bytes.iter().any(|address| bloom_contains(bloom, address))
+        };
+
+        if !needed {
+            counter!("receipt_fetches_skipped_total").increment(1);
+        }
+        needed
+    }
+
+    /// Drops transactions, logs, authorizations and state diffs that match no
+    /// criterion.
+    /// A transaction is kept if it matches directly or emitted a matching log.
+    pub fn apply(&self, block: &mut Block) {
+        if !self.is_active() {
+            return;
+        }
+
+        let matched: HashSet<String> = block.transactions.iter()
+            .filter(|tx| self.matches_transaction(tx))
+            .map(|tx| tx.hash.clone())
+            .collect();
+        // Logs of matched transactions are kept whole; otherwise only logs
+        // from the configured emitters.
+        block.logs.retain(|log| {
+            matched.contains(&log.transaction_hash) || self.config.log_emitters.contains(&log.address)
+        });
+
+        let mut kept = matched;
+        kept.extend(block.logs.iter().map(|log| log.transaction_hash.clone()));
+
+        let total = block.transactions.len();
+        block.transactions.retain(|tx| kept.contains(&tx.hash));
+        block.authorizations.retain(|authorization| kept.contains(&authorization.tx_hash));
+        block.state_diffs.retain(|diff| kept.contains(&diff.transaction_hash));
+
+        counter!("filter_transactions_total", "result" => "kept").increment(block.transactions.len() as u64);
+        counter!("filter_transactions_total", "result" => "dropped")
+            .increment((total - block.transactions.len()) as u64);
+    }
+}
+
+/// Whether `input` may be in a 2048-bit logs bloom: the three bits chosen by
+/// the first three byte pairs of its keccak hash must all be set.
+pub fn bloom_contains(bloom: &[u8], input: &[u8]) -> bool {
+    let hash = keccak256(input);
+    (0..3).all(|i| {
+        let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+        bloom[255 - bit / 8] & (1 << (bit % 8)) != 0
+    })
+}
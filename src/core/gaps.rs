@@ -0,0 +1,345 @@
+use crate::core::checkpoints;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{info, warn};
+
+/// Name of the file in `data_dir` holding the indexed block ranges.
+const INDEXED_BLOCKS_FILE: &str = "indexed_blocks.json";
+
+/// Set of block numbers stored as disjoint, non-adjacent inclusive ranges.
+#[derive(Debug, Clone, Default)]
+pub struct RangeSet {
+    /// Range start to range end, both inclusive.
+    ranges: BTreeMap<u64, u64>,
+}
+
+impl RangeSet {
+    pub fn insert(&mut self, number: u64) {
+        self.insert_range(number, number);
+    }
+
+    pub fn insert_range(&mut self, mut start: u64, mut end: u64) {
+        // Absorb a range that overlaps or touches `start` from the left.
+        if let Some((&prev_start, &prev_end)) = self.ranges.range(..=start).next_back() {
+            if prev_end.saturating_add(1) >= start {
+                start = prev_start;
+                end = end.max(prev_end);
+            }
+        }
+        // Absorb every range starting inside or right after the new one.
+        let following: Vec<(u64, u64)> = self.ranges
+            .range(start..=end.saturating_add(1))
+            .map(|(&s, &e)| (s, e))
+            .collect();
+        for (s, e) in following {
+            self.ranges.remove(&s);
+            end = end.max(e);
+        }
+        self.ranges.insert(start, end);
+    }
+
+    pub fn remove_range(&mut self, start: u64, end: u64) {
+        let overlapping: Vec<(u64, u64)> = self.iter()
+            .filter(|&(s, e)| s <= end && e >= start)
+            .collect();
+        for (s, e) in overlapping {
+            self.ranges.remove(&s);
+            if s < start {
+                self.ranges.insert(s, start - 1);
+            }
+            if e > end {
+                self.ranges.insert(end + 1, e);
+            }
+        }
+    }
+
+    pub fn extend(&mut self, other: &RangeSet) {
+        for (&start, &end) in &other.ranges {
+            self.insert_range(start, end);
+        }
+    }
+
+    pub fn first(&self) -> Option<u64> {
+        self.ranges.keys().next().copied()
+    }
+
+    pub fn last(&self) -> Option<u64> {
+        self.ranges.values().next_back().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.ranges.iter().map(|(&start, &end)| (start, end))
+    }
+
+    /// Inclusive ranges in `start..=end` that are not in the set.
+    pub fn gaps(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let mut gaps = Vec::new();
+        if start > end {
+            return gaps;
+        }
+
+        let mut next = start;
+        let covering = self.ranges.range(..=start).next_back();
+        let inside = self.ranges.range(start.saturating_add(1)..=end);
+        for (&range_start, &range_end) in covering.into_iter().chain(inside) {
+            if range_end < next {
+                continue;
+            }
+            if range_start > next {
+                gaps.push((next, range_start - 1));
+            }
+            match range_end.checked_add(1) {
+                Some(after) if after <= end => next = after,
+                _ => return gaps,
+            }
+        }
+        gaps.push((next, end));
+        gaps
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexedBlocksFile {
+    ranges: Vec<(u64, u64)>,
+    /// Blocks dead-lettered and skipped under the malformed block policy.
+    #[serde(default)]
+    skipped: Vec<(u64, u64)>,
+}
+
+#[derive(Debug, Default)]
+struct IndexedBlocksState {
+    /// Blocks in finalized parquet files, persisted to disk.
+    indexed: RangeSet,
+    /// Blocks written to files that are still open.
+    written: RangeSet,
+    /// Ranges owned by a backfill worker that has not finished them yet.
+    claimed: RangeSet,
+    /// Blocks skipped as malformed, persisted so gap scans leave them out.
+    skipped: RangeSet,
+}
+
+/// Tracks which blocks have been stored, shared between the storage task and
+/// the backfill worker. Only blocks in closed files are persisted, so blocks
+/// lost with an unfinished file show up as gaps after a restart.
+#[derive(Debug)]
+pub struct IndexedBlocks {
+    path: PathBuf,
+    state: Mutex<IndexedBlocksState>,
+}
+
+impl IndexedBlocks {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(INDEXED_BLOCKS_FILE);
+
+        let mut indexed = RangeSet::default();
+        let mut skipped = RangeSet::default();
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let file: IndexedBlocksFile = serde_json::from_slice(&bytes)?;
+                for (start, end) in file.ranges {
+                    indexed.insert_range(start, end);
+                }
+                for (start, end) in file.skipped {
+                    skipped.insert_range(start, end);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        if let (Some(first), Some(last)) = (indexed.first(), indexed.last()) {
+            let mut handled = indexed.clone();
+            handled.extend(&skipped);
+            let holes = handled.gaps(first, last);
+            let missing: u64 = holes.iter().map(|(start, end)| end - start + 1).sum();
+            info!(
+                event = "indexed_blocks_loaded",
+                message = "Loaded indexed block ranges",
+                first_block = first,
+                last_block = last,
+                gap_count = holes.len(),
+                missing_blocks = missing
+            );
+            for (start, end) in holes.iter().take(20) {
+                warn!(
+                    event = "block_gap_detected",
+                    message = "Blocks missing from indexed range",
+                    start_block = start,
+                    end_block = end
+                );
+            }
+        }
+
+        Ok(Self {
+            path,
+            state: Mutex::new(IndexedBlocksState { indexed, skipped, ..Default::default() }),
+        })
+    }
+
+    /// Records blocks handed to an open parquet writer.
+    pub fn mark_written(&self, numbers: impl IntoIterator<Item = u64>) {
+        let mut state = self.state.lock().unwrap();
+        for number in numbers {
+            state.written.insert(number);
+        }
+    }
+
+    /// Moves `blocks` from the written into the indexed set once their files
+    /// are closed, and persists the result.
+    pub fn mark_published(&self, blocks: &RangeSet) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        for (start, end) in blocks.iter() {
+            state.written.remove_range(start, end);
+        }
+        state.indexed.extend(blocks);
+        self.persist(&state)
+    }
+
+    /// Records a block that was dead-lettered instead of stored, so it is
+    /// not fetched again.
+    pub fn mark_skipped(&self, number: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.skipped.insert(number);
+        self.persist(&state)
+    }
+
+    fn persist(&self, state: &IndexedBlocksState) -> Result<()> {
+        let file = IndexedBlocksFile {
+            ranges: state.indexed.iter().collect(),
+            skipped: state.skipped.iter().collect(),
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&file)?)?;
+        // Synced before and after the rename so a crash leaves either the
+        // old or the new file, never a truncated one.
+        checkpoints::sync_file(&tmp)?;
+        std::fs::rename(&tmp, &self.path)?;
+        if let Some(dir) = self.path.parent() {
+            checkpoints::sync_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Blocks that have been written, whether or not their files are closed.
+    pub fn stored(&self) -> RangeSet {
+        let state = self.state.lock().unwrap();
+        let mut all = state.indexed.clone();
+        all.extend(&state.written);
+        all
+    }
+
+    /// Blocks that have been stored or skipped as malformed.
+    pub fn handled(&self) -> RangeSet {
+        let state = self.state.lock().unwrap();
+        let mut all = state.indexed.clone();
+        all.extend(&state.written);
+        all.extend(&state.skipped);
+        all
+    }
+
+    /// Blocks that have been handled or that a backfill worker is
+    /// responsible for; everything else below the head is a gap.
+    pub fn snapshot(&self) -> RangeSet {
+        let mut all = self.handled();
+        all.extend(&self.state.lock().unwrap().claimed);
+        all
+    }
+
+    pub fn claim(&self, start: u64, end: u64) {
+        self.state.lock().unwrap().claimed.insert_range(start, end);
+    }
+
+    /// Hands a finished range back; blocks in it that were not stored become
+    /// gaps for the gap worker.
+    pub fn release(&self, start: u64, end: u64) {
+        self.state.lock().unwrap().claimed.remove_range(start, end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(u64, u64)]) -> RangeSet {
+        let mut set = RangeSet::default();
+        for &(start, end) in ranges {
+            set.insert_range(start, end);
+        }
+        set
+    }
+
+    #[test]
+    fn insert_range_merges_overlapping_and_adjacent_ranges() {
+        let mut ranges = set(&[(10, 19), (30, 39)]);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(10, 19), (30, 39)]);
+
+        ranges.insert_range(20, 21);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(10, 21), (30, 39)]);
+
+        ranges.insert_range(25, 29);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(10, 21), (25, 39)]);
+
+        ranges.insert_range(5, 50);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(5, 50)]);
+
+        ranges.insert(52);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(5, 50), (52, 52)]);
+        assert_eq!((ranges.first(), ranges.last()), (Some(5), Some(52)));
+    }
+
+    #[test]
+    fn insert_range_handles_the_end_of_the_number_line() {
+        let ranges = set(&[(u64::MAX - 1, u64::MAX), (0, 0), (u64::MAX - 3, u64::MAX - 2)]);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(0, 0), (u64::MAX - 3, u64::MAX)]);
+    }
+
+    #[test]
+    fn remove_range_splits_ranges() {
+        let mut ranges = set(&[(10, 30)]);
+        ranges.remove_range(15, 20);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(10, 14), (21, 30)]);
+        ranges.remove_range(0, 12);
+        assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![(13, 14), (21, 30)]);
+    }
+
+    #[test]
+    fn gaps_lists_missing_ranges_within_bounds() {
+        let ranges = set(&[(10, 19), (30, 39)]);
+        assert_eq!(ranges.gaps(10, 39), vec![(20, 29)]);
+        assert_eq!(ranges.gaps(0, 50), vec![(0, 9), (20, 29), (40, 50)]);
+        assert_eq!(ranges.gaps(15, 32), vec![(20, 29)]);
+        assert_eq!(ranges.gaps(12, 18), vec![]);
+        assert_eq!(ranges.gaps(21, 25), vec![(21, 25)]);
+        assert_eq!(ranges.gaps(20, 10), vec![]);
+        assert_eq!(RangeSet::default().gaps(3, 5), vec![(3, 5)]);
+        assert_eq!(set(&[(0, u64::MAX)]).gaps(0, u64::MAX), vec![]);
+    }
+
+    #[test]
+    fn skipped_blocks_are_handled_and_persisted() {
+        let dir = std::env::temp_dir().join(format!("indexed_blocks_test_{}", std::process::id()));
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        indexed.mark_published(&set(&[(10, 19)])).unwrap();
+        indexed.mark_skipped(20).unwrap();
+        indexed.mark_written([21]);
+        assert_eq!(indexed.stored().iter().collect::<Vec<_>>(), vec![(10, 19), (21, 21)]);
+        assert_eq!(indexed.handled().iter().collect::<Vec<_>>(), vec![(10, 21)]);
+
+        let reopened = IndexedBlocks::open(&dir).unwrap();
+        assert_eq!(reopened.handled().iter().collect::<Vec<_>>(), vec![(10, 20)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
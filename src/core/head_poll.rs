@@ -0,0 +1,86 @@
+use crate::config::HeadPollConfig;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of recent blocks used to estimate the block interval.
+const INTERVAL_WINDOW: usize = 64;
+/// Interval assumed until enough blocks have been seen.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Schedules `eth_blockNumber` polls around the expected arrival of the next
+/// block, based on the interval between recent block timestamps.
+#[derive(Debug)]
+pub struct HeadPollScheduler {
+    config: HeadPollConfig,
+    /// `(number, timestamp)` of recently processed blocks, oldest first.
+    samples: VecDeque<(u64, u64)>,
+    /// Polls in a row that found no new block since the last one did.
+    misses: u32,
+}
+
+impl HeadPollScheduler {
+    pub fn new(config: &HeadPollConfig) -> Self {
+        Self {
+            config: config.clone(),
+            samples: VecDeque::with_capacity(INTERVAL_WINDOW),
+            misses: 0,
+        }
+    }
+
+    pub fn observe_block(&mut self, number: u64, timestamp: u64) {
+        if self.samples.back().is_some_and(|&(last, _)| number <= last) {
+            return;
+        }
+        if self.samples.len() == INTERVAL_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((number, timestamp));
+    }
+
+    pub fn record_poll(&mut self, found_new_block: bool) {
+        if found_new_block {
+            self.misses = 0;
+        } else {
+            self.misses = self.misses.saturating_add(1);
+        }
+    }
+
+    /// Average time between blocks over the window. Timestamps only have
+    /// second resolution, so sub-second chains need the whole window.
+    pub fn block_interval(&self) -> Option<Duration> {
+        let (&(first_number, first_ts), &(last_number, last_ts)) =
+            (self.samples.front()?, self.samples.back()?);
+        if last_number == first_number || last_ts < first_ts {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            (last_ts - first_ts) as f64 / (last_number - first_number) as f64,
+        ))
+    }
+
+    /// How long to wait before the next head poll.
+    pub fn next_poll_delay(&self) -> Duration {
+        let min = Duration::from_millis(self.config.min_interval_ms);
+        let max = Duration::from_millis(self.config.max_interval_ms).max(min);
+        let interval = self.block_interval().unwrap_or(DEFAULT_INTERVAL);
+
+        let expected_in = self.samples.back().and_then(|&(_, timestamp)| {
+            let expected = Duration::from_secs(timestamp) + interval;
+            expected.checked_sub(unix_now())
+        });
+
+        let delay = match expected_in {
+            // The next block is not due yet; poll right when it should land.
+            Some(until_expected) if self.misses == 0 => until_expected,
+            // It is overdue or we just missed it: poll more often, backing
+            // off towards one interval as misses accumulate.
+            _ => (min * 2u32.saturating_pow(self.misses.min(16))).min(interval),
+        };
+
+        delay.clamp(min, max)
+    }
+}
+
+pub fn unix_now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
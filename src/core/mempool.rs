@@ -0,0 +1,416 @@
+use crate::config::Config;
+use crate::core::checkpoints::{self, TEMP_SUFFIX};
+use crate::core::datasets::PendingTransactionsDataset;
+use crate::core::decode::decode_pending_transaction;
+use crate::core::head_poll::unix_now;
+use crate::core::shutdown::Shutdown;
+use crate::models::PendingTransaction;
+use crate::rpc::{RateLimitedTransport, RateLimiter};
+use crate::utils::error::IndexerError;
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::{FuturesUnordered, StreamExt};
+use metrics::counter;
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::File,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+use web3::{api::SubscriptionId, transports::WebSocket, DuplexTransport, Transport};
+
+/// Pending transactions buffered before they are handed to the writer.
+const FLUSH_ROWS: usize = 1000;
+/// How often buffered transactions are written even if fewer arrived.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+/// Age at which a file is closed, making it readable, and a new one started.
+const FILE_DURATION: Duration = Duration::from_secs(600);
+/// Hashes remembered to drop re-announcements of the same transaction.
+const SEEN_CAPACITY: usize = 200_000;
+/// `eth_getTransactionByHash` requests in flight on hash-only nodes.
+const MAX_LOOKUPS: usize = 32;
+/// Announced hashes waiting for a lookup. Beyond this only the hash is kept.
+const MAX_QUEUED_LOOKUPS: usize = 10_000;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Records transactions announced over a `newPendingTransactions`
+/// WebSocket subscription into `pending_transactions_<time>.parquet` files.
+/// Requests go through the endpoint's rate limiter like any other fetch.
+pub struct MempoolRecorder {
+    endpoint: String,
+    limiter: Arc<RateLimiter>,
+    data_dir: PathBuf,
+    dataset: PendingTransactionsDataset,
+    /// Open writer, its file name without extension and when it was created.
+    writer: Option<(ArrowWriter<File>, String, Instant)>,
+    buffer: Vec<PendingTransaction>,
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    /// Hashes announced without a transaction and when they were first seen.
+    lookups: VecDeque<(String, u64)>,
+    shutdown: Shutdown,
+}
+
+impl MempoolRecorder {
+    pub fn new(config: &Config, endpoint: String, limiter: Arc<RateLimiter>, shutdown: Shutdown) -> Self {
+        Self {
+            endpoint,
+            limiter,
+            data_dir: config.data_dir.clone(),
+            dataset: PendingTransactionsDataset::new(config.column_types),
+            writer: None,
+            buffer: Vec::with_capacity(FLUSH_ROWS),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            lookups: VecDeque::new(),
+            shutdown,
+        }
+    }
+
+    /// Records until shutdown or until a file cannot be written,
+    /// resubscribing whenever the connection drops.
+    pub async fn run(mut self) -> Result<()> {
+        loop {
+            let shutdown = self.shutdown.clone();
+            let result = tokio::select! {
+                result = self.subscribe_and_record() => result,
+                _ = shutdown.requested() => {
+                    while let Some((hash, first_seen_ms)) = self.lookups.pop_front() {
+                        self.record(&Value::Null, first_seen_ms, Some(hash))?;
+                    }
+                    self.flush()?;
+                    self.close_file()?;
+                    info!(
+                        event = "mempool_recorder_stopped",
+                        message = "Shutdown requested, pending transaction file closed"
+                    );
+                    return Ok(());
+                }
+            };
+            match result {
+                Ok(()) => warn!(
+                    event = "mempool_subscription_ended",
+                    message = "Pending transaction subscription closed by the node"
+                ),
+                Err(e) => error!(
+                    event = "mempool_subscription_error",
+                    message = "Pending transaction subscription failed",
+                    error = %e
+                ),
+            }
+            self.flush()?;
+            counter!("mempool_reconnects_total").increment(1);
+            self.shutdown.sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn subscribe_and_record(&mut self) -> Result<()> {
+        let ws = WebSocket::new(&self.endpoint)
+            .await
+            .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+        let requests = RateLimitedTransport::new(ws.clone(), self.limiter.clone());
+
+        // Ask for full transaction objects; nodes that do not support it
+        // reject the flag and only announce hashes.
+        let (id, full_transactions) = match requests
+            .execute("eth_subscribe", vec![json!("newPendingTransactions"), json!(true)])
+            .await
+        {
+            Ok(id) => (id, true),
+            Err(web3::Error::Rpc(_)) => {
+                let id = requests.execute("eth_subscribe", vec![json!("newPendingTransactions")])
+                    .await
+                    .map_err(|e| IndexerError::RpcError(e.to_string()))?;
+                (id, false)
+            }
+            Err(e) => return Err(IndexerError::RpcError(e.to_string()).into()),
+        };
+        let id = id.as_str()
+            .ok_or_else(|| IndexerError::RpcError(format!("Unexpected subscription id: {}", id)))?
+            .to_string();
+        let mut notifications = ws.subscribe(SubscriptionId::from(id))?;
+        info!(
+            event = "mempool_subscribed",
+            message = "Subscribed to pending transactions",
+            endpoint = %self.endpoint,
+            full_transactions = full_transactions
+        );
+
+        // Transactions announced by hash only, being fetched.
+        let mut lookups = FuturesUnordered::new();
+        let mut flush_timer = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            while lookups.len() < MAX_LOOKUPS {
+                let Some((hash, first_seen_ms)) = self.lookups.pop_front() else {
+                    break;
+                };
+                let requests = requests.clone();
+                lookups.push(async move {
+                    let raw = requests.execute("eth_getTransactionByHash", vec![json!(hash)]).await;
+                    (hash, first_seen_ms, raw)
+                });
+            }
+
+            tokio::select! {
+                notification = notifications.next() => {
+                    let Some(notification) = notification else {
+                        return Ok(());
+                    };
+                    let first_seen_ms = unix_now().as_millis() as u64;
+                    match notification {
+                        Value::String(hash) => {
+                            let hash = hash.to_lowercase();
+                            if self.mark_seen(&hash) {
+                                self.queue_lookup(hash, first_seen_ms)?;
+                            }
+                        }
+                        raw => {
+                            let hash = raw.get("hash").and_then(Value::as_str).map(str::to_lowercase);
+                            if hash.is_some_and(|hash| self.mark_seen(&hash)) {
+                                self.record(&raw, first_seen_ms, None)?;
+                            }
+                        }
+                    }
+                }
+                Some((hash, first_seen_ms, raw)) = lookups.next() => {
+                    // A transaction can be mined or dropped before it is
+                    // fetched; its hash and first-seen time are kept anyway.
+                    let raw = raw.unwrap_or(Value::Null);
+                    self.record(&raw, first_seen_ms, Some(hash))?;
+                }
+                _ = flush_timer.tick() => self.flush()?,
+            }
+        }
+    }
+
+    /// Returns `false` if `hash` was seen recently.
+    fn mark_seen(&mut self, hash: &str) -> bool {
+        if !self.seen.insert(hash.to_string()) {
+            counter!("pending_transactions_duplicate_total").increment(1);
+            return false;
+        }
+        self.seen_order.push_back(hash.to_string());
+        if self.seen_order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Queues `hash` to be fetched, or records only the hash if too many
+    /// lookups are already waiting.
+    fn queue_lookup(&mut self, hash: String, first_seen_ms: u64) -> Result<()> {
+        if self.lookups.len() >= MAX_QUEUED_LOOKUPS {
+            counter!("pending_transaction_lookups_dropped_total").increment(1);
+            return self.record(&Value::Null, first_seen_ms, Some(hash));
+        }
+        self.lookups.push_back((hash, first_seen_ms));
+        Ok(())
+    }
+
+    /// Buffers a transaction object, or only `hash` if it is missing or
+    /// cannot be decoded.
+    fn record(&mut self, raw: &Value, first_seen_ms: u64, hash: Option<String>) -> Result<()> {
+        let transaction = match (raw.is_null(), hash) {
+            (true, Some(hash)) => hash_only(hash, first_seen_ms),
+            (_, hash) => match decode_pending_transaction(raw, first_seen_ms) {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    counter!("pending_transactions_undecodable_total").increment(1);
+                    let Some(hash) = hash.or_else(|| raw.get("hash").and_then(Value::as_str).map(str::to_lowercase))
+                    else {
+                        return Ok(());
+                    };
+                    warn!(
+                        event = "pending_transaction_undecodable",
+                        message = "Keeping only the hash of a pending transaction",
+                        hash = %hash,
+                        error = %e
+                    );
+                    hash_only(hash, first_seen_ms)
+                }
+            },
+        };
+
+        counter!("pending_transactions_total").increment(1);
+        self.buffer.push(transaction);
+        if self.buffer.len() >= FLUSH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes buffered transactions and closes the file once it is old enough.
+    fn flush(&mut self) -> Result<()> {
+        if !self.buffer.is_empty() {
+            if self.writer.is_none() {
+                let stem = format!("{}_{}", self.dataset.name(), Utc::now().format("%Y%m%d_%H%M%S"));
+                let props = WriterProperties::builder()
+                    .set_compression(parquet::basic::Compression::SNAPPY)
+                    .build();
+                let file = File::create(self.data_dir.join(format!("{}{}", stem, TEMP_SUFFIX)))?;
+                self.writer = Some((ArrowWriter::try_new(file, self.dataset.schema(), Some(props))?, stem, Instant::now()));
+            }
+            if let Some((writer, _, _)) = &mut self.writer {
+                writer.write(&self.dataset.build_batch(&self.buffer)?)?;
+            }
+            self.buffer.clear();
+        }
+
+        if self.writer.as_ref().is_some_and(|(_, _, opened)| opened.elapsed() >= FILE_DURATION) {
+            self.close_file()?;
+        }
+        Ok(())
+    }
+
+    /// Finalizes the open file and publishes it under its final name.
+    fn close_file(&mut self) -> Result<()> {
+        if let Some((writer, stem, _)) = self.writer.take() {
+            let temp = self.data_dir.join(format!("{}{}", stem, TEMP_SUFFIX));
+            writer.close()?;
+            checkpoints::sync_file(&temp)?;
+            std::fs::rename(&temp, self.data_dir.join(format!("{}.parquet", stem)))?;
+            checkpoints::sync_dir(&self.data_dir)?;
+        }
+        Ok(())
+    }
+}
+
+fn hash_only(hash: String, first_seen_ms: u64) -> PendingTransaction {
+    PendingTransaction {
+        hash,
+        first_seen_ms,
+        from: None,
+        to: None,
+        nonce: None,
+        value: None,
+        gas: None,
+        gas_price: None,
+        max_fee_per_gas: None,
+        max_priority_fee_per_gas: None,
+        input: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ColumnTypes, RateLimitConfig};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    const HASH: &str = "0xabababababababababababababababababababababababababababababababab";
+
+    fn recorder(name: &str) -> MempoolRecorder {
+        let data_dir = std::env::temp_dir().join(format!("mempool_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let config = RateLimitConfig {
+            requests_per_second: None,
+            compute_units_per_second: None,
+            method_compute_units: Default::default(),
+            default_compute_units: 1,
+            daily_compute_units: None,
+            monthly_compute_units: None,
+            head_reserve_percent: 10,
+        };
+        let limiter = RateLimiter::new(&config, "test", &data_dir, Shutdown::new()).unwrap();
+        MempoolRecorder {
+            endpoint: "ws://localhost".to_string(),
+            limiter: Arc::new(limiter),
+            data_dir,
+            dataset: PendingTransactionsDataset::new(ColumnTypes::Strings),
+            writer: None,
+            buffer: Vec::new(),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            lookups: VecDeque::new(),
+            shutdown: Shutdown::new(),
+        }
+    }
+
+    fn transaction() -> Value {
+        json!({
+            "hash": HASH,
+            "nonce": "0x7",
+            "blockHash": null,
+            "blockNumber": null,
+            "transactionIndex": null,
+            "from": "0x1111111111111111111111111111111111111111",
+            "to": "0x2222222222222222222222222222222222222222",
+            "value": "0xde0b6b3a7640000",
+            "gasPrice": "0x3b9aca00",
+            "gas": "0x5208",
+            "input": "0x",
+        })
+    }
+
+    #[test]
+    fn drops_repeated_announcements() {
+        let mut recorder = recorder("seen");
+        assert!(recorder.mark_seen(HASH));
+        assert!(!recorder.mark_seen(HASH));
+
+        // The oldest hash is forgotten once the window is full.
+        for index in 0..SEEN_CAPACITY {
+            assert!(recorder.mark_seen(&format!("0x{:064x}", index)));
+        }
+        assert_eq!(recorder.seen.len(), SEEN_CAPACITY);
+        assert!(recorder.mark_seen(HASH));
+        assert!(!recorder.mark_seen(&format!("0x{:064x}", SEEN_CAPACITY - 1)));
+    }
+
+    #[test]
+    fn records_full_transactions_and_hashes() {
+        let mut recorder = recorder("record");
+        recorder.record(&transaction(), 1_000, None).unwrap();
+        // Mined or dropped before the lookup answered.
+        recorder.record(&Value::Null, 2_000, Some("0x01".to_string())).unwrap();
+        // Undecodable, with and without a hash to keep.
+        recorder.record(&json!({ "hash": "0x02" }), 3_000, None).unwrap();
+        recorder.record(&json!({ "nonce": "0x1" }), 4_000, None).unwrap();
+
+        let full = &recorder.buffer[0];
+        assert_eq!((full.hash.as_str(), full.first_seen_ms), (HASH, 1_000));
+        assert_eq!(full.nonce, Some(7));
+        assert_eq!(full.value.as_deref(), Some("1000000000000000000"));
+        assert_eq!(full.gas, Some(21_000));
+        let hashes: Vec<_> = recorder.buffer[1..].iter()
+            .map(|transaction| (transaction.hash.as_str(), transaction.first_seen_ms, transaction.from.is_none()))
+            .collect();
+        assert_eq!(hashes, vec![("0x01", 2_000, true), ("0x02", 3_000, true)]);
+    }
+
+    #[test]
+    fn keeps_only_the_hash_once_lookups_back_up() {
+        let mut recorder = recorder("queue");
+        for index in 0..MAX_QUEUED_LOOKUPS + 2 {
+            recorder.queue_lookup(format!("0x{:064x}", index), index as u64).unwrap();
+        }
+        assert_eq!(recorder.lookups.len(), MAX_QUEUED_LOOKUPS);
+        let recorded: Vec<_> = recorder.buffer.iter().map(|transaction| transaction.first_seen_ms).collect();
+        assert_eq!(recorded, vec![MAX_QUEUED_LOOKUPS as u64, MAX_QUEUED_LOOKUPS as u64 + 1]);
+    }
+
+    #[test]
+    fn publishes_recorded_transactions() {
+        let mut recorder = recorder("file");
+        std::fs::create_dir_all(&recorder.data_dir).unwrap();
+        recorder.record(&transaction(), 1_000, None).unwrap();
+        recorder.record(&Value::Null, 2_000, Some("0x01".to_string())).unwrap();
+        recorder.flush().unwrap();
+        recorder.close_file().unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&recorder.data_dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "parquet"))
+            .collect();
+        assert_eq!(files.len(), 1);
+        let reader = SerializedFileReader::new(File::open(&files[0]).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        std::fs::remove_dir_all(&recorder.data_dir).unwrap();
+    }
+}
@@ -1,14 +1,12 @@
 use anyhow::Result;
 use metrics::{counter, gauge, histogram};
-use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use metrics_exporter_prometheus::PrometheusBuilder;
 use crate::models::Block;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tracing::info;
 
 #[derive(Clone)]
-pub struct MetricsCollector {
-    port: u16,
-}
+pub struct MetricsCollector;
 
 impl MetricsCollector {
     pub fn new(port: u16) -> Result<Self> {
@@ -31,12 +29,19 @@ impl MetricsCollector {
             endpoint = format!("http://0.0.0.0:{}/metrics", port)
         );
 
-        Ok(Self { port })
+        Ok(Self)
     }
 
     pub fn record_block(&self, block: &Block) {
         counter!("blocks_processed_total").increment(1);
         counter!("transactions_processed_total").increment(block.transactions.len() as u64);
+        counter!("authorizations_processed_total").increment(block.authorizations.len() as u64);
+        counter!("uncles_processed_total").increment(block.uncles.len() as u64);
+        counter!("logs_processed_total").increment(block.logs.len() as u64);
+        counter!("token_transfers_processed_total").increment(block.token_transfers.len() as u64);
+        counter!("tokens_discovered_total").increment(block.tokens.len() as u64);
+        counter!("contracts_processed_total").increment(block.contracts.len() as u64);
+        counter!("state_diffs_processed_total").increment(block.state_diffs.len() as u64);
         gauge!("latest_block_number").set(block.number as f64);
         gauge!("latest_block_timestamp").set(block.timestamp as f64);
         gauge!("block_transaction_count").set(block.transactions.len() as f64);
@@ -47,6 +52,34 @@ impl MetricsCollector {
         histogram!("block_processing_time_seconds").record(duration.as_secs_f64());
     }
 
+    pub fn record_malformed_block(&self, action: &str) {
+        counter!("malformed_blocks_total", "action" => action.to_string()).increment(1);
+        if action == "skip" {
+            counter!("blocks_skipped_total").increment(1);
+        }
+    }
+
+    pub fn record_head_poll(&self, block_interval: Option<Duration>, next_poll: Duration) {
+        if let Some(interval) = block_interval {
+            gauge!("block_interval_seconds").set(interval.as_secs_f64());
+        }
+        gauge!("head_poll_delay_seconds").set(next_poll.as_secs_f64());
+    }
+
+    /// Time from a block's timestamp until the poll that first saw it.
+    pub fn record_head_detection_latency(&self, latency: Duration) {
+        histogram!("head_detection_latency_seconds").record(latency.as_secs_f64());
+    }
+
+    pub fn record_gaps(&self, gap_count: usize, missing_blocks: u64) {
+        gauge!("gap_count").set(gap_count as f64);
+        gauge!("missing_blocks").set(missing_blocks as f64);
+    }
+
+    pub fn record_backfilled_block(&self) {
+        counter!("backfilled_blocks_total").increment(1);
+    }
+
     pub fn record_sync_status(&self, current_block: u64, latest_block: u64) {
         gauge!("current_processing_block").set(current_block as f64);
         gauge!("chain_latest_block").set(latest_block as f64);
@@ -1,65 +1,270 @@
+mod abi;
+mod authorization;
+mod backfill;
 mod block_processor;
+mod checkpoints;
+mod contracts;
+mod datasets;
+mod dead_letter;
+mod decode;
+mod filter;
+mod gaps;
+mod head_poll;
+mod mempool;
 mod metrics;
+mod resume;
+mod shutdown;
+mod signatures;
+mod state_diffs;
 mod storage;
+mod token_transfers;
+mod tokens;
 
 use anyhow::Result;
-use std::sync::Arc;
+use crossbeam::channel::TryRecvError;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
 use tokio::sync::Mutex;
-use crate::config::Config;
+use crate::config::{BlockSource, Config};
+use crate::rpc;
+use crate::utils::error::IndexerError;
 use futures::future::try_join_all;
-use tracing::{info, error};
+use tracing::{info, warn};
+use abi::AbiRegistry;
+use gaps::IndexedBlocks;
 
 pub use block_processor::BlockProcessor;
 pub use metrics::MetricsCollector;
+pub use shutdown::Shutdown;
 pub use storage::StorageManager;
 
 pub struct Indexer {
     block_processor: Arc<BlockProcessor>,
     storage_manager: Arc<Mutex<StorageManager>>,
+    indexed_blocks: Arc<IndexedBlocks>,
+    abi: Arc<AbiRegistry>,
     metrics_collector: MetricsCollector,
+    shutdown: Shutdown,
     config: Config,
 }
 
 impl Indexer {
     pub async fn new(config: Config) -> Result<Self> {
         let metrics_collector = MetricsCollector::new(config.metrics_port)?;
-        let storage_manager = Arc::new(Mutex::new(StorageManager::new(&config)?));
-        let block_processor = Arc::new(BlockProcessor::new(&config, metrics_collector.clone()).await?);
+        let indexed_blocks = Arc::new(IndexedBlocks::open(&config.data_dir)?);
+        checkpoints::recover_temp_files(&config.data_dir, &indexed_blocks)?;
+        let abi = Arc::new(AbiRegistry::load(config.abi_dir.as_deref(), config.column_types)?);
+        let shutdown = Shutdown::new();
+        let block_processor = Arc::new(
+            BlockProcessor::new(
+                &config,
+                metrics_collector.clone(),
+                abi.clone(),
+                indexed_blocks.clone(),
+                shutdown.clone(),
+            )
+            .await?,
+        );
+        let storage_manager = Arc::new(Mutex::new(StorageManager::new(
+            &config,
+            indexed_blocks.clone(),
+            &abi,
+            block_processor.tokens(),
+        )?));
 
         Ok(Self {
             block_processor,
             storage_manager,
+            indexed_blocks,
+            abi,
             metrics_collector,
+            shutdown,
             config,
         })
     }
 
+    /// Hands `ranges` of history to the backfill workers, at least one even
+    /// when `backfill_workers` is 0.
+    fn spawn_backfill_workers(&self, ranges: &[(u64, u64)]) -> Vec<tokio::task::JoinHandle<Result<()>>> {
+        let (Some(&(first, _)), Some(&(_, last))) = (ranges.first(), ranges.last()) else {
+            return Vec::new();
+        };
+
+        let queue = Arc::new(backfill::RangeQueue::split(ranges, self.config.backfill_range_blocks));
+        // Keeps the gap worker away from history the range workers own.
+        for &(start, end) in ranges {
+            self.indexed_blocks.claim(start, end);
+        }
+
+        let workers = self.config.backfill_workers.max(1);
+        info!(
+            event = "backfill_workers_started",
+            message = "Indexing history in parallel with the head",
+            workers = workers,
+            first_block = first,
+            last_block = last,
+            ranges = ranges.len()
+        );
+
+        (0..workers)
+            .map(|worker| {
+                self.shutdown.spawn("backfill_worker", rpc::as_backfill(backfill::run_worker(
+                    worker,
+                    self.block_processor.clone(),
+                    self.config.clone(),
+                    self.indexed_blocks.clone(),
+                    self.abi.clone(),
+                    queue.clone(),
+                    self.metrics_collector.clone(),
+                    self.shutdown.clone(),
+                )))
+            })
+            .collect()
+    }
+
     pub async fn run(&self) -> Result<()> {
         let block_receiver = self.block_processor.get_blocks_receiver();
-        let config_start_block = self.config.start_block;
+        let mut config_start_block = self.config.start_block;
+        // History below the head follower's start for the backfill workers.
+        let mut history = Vec::new();
+        if let (BlockSource::Rpc, None) = (self.config.block_source, config_start_block) {
+            if let Some(point) = resume::find_resume_point(&self.config.data_dir, &self.indexed_blocks)? {
+                self.block_processor.verify_parent(point.block, &point.parent_hash).await?;
+                info!(
+                    event = "resuming_from_stored_data",
+                    message = "Continuing after the highest stored block",
+                    start_block = point.block,
+                    parent_hash = %point.parent_hash
+                );
+                config_start_block = Some(point.block);
+
+                // Holes below the resume point are filled whether or not the
+                // gap worker runs.
+                let handled = self.indexed_blocks.handled();
+                let holes = handled.gaps(handled.first().unwrap_or(point.block), point.block - 1);
+                if !holes.is_empty() {
+                    info!(
+                        event = "resume_holes_scheduled",
+                        message = "Backfilling blocks missing below the resume point",
+                        hole_count = holes.len(),
+                        missing_blocks = holes.iter().map(|(start, end)| end - start + 1).sum::<u64>()
+                    );
+                    history.extend(holes);
+                }
+            }
+        }
+        if let (BlockSource::Rpc, Some(start), true) =
+            (self.config.block_source, config_start_block, self.config.backfill_workers > 0)
+        {
+            let head = self.block_processor.get_latest_block_number().await?;
+            if start < head {
+                history.push((start, head - 1));
+                config_start_block = Some(head);
+            }
+        }
+        let backfill_handles = self.spawn_backfill_workers(&history);
         let processor = self.block_processor.clone();
         let storage = self.storage_manager.clone();
         let metrics = self.metrics_collector.clone();
+        let block_source = self.config.block_source;
+        let source_path = self.config.source_path.clone();
+        let finished = Arc::new(AtomicBool::new(false));
+        let processor_finished = finished.clone();
+        let processor_shutdown = self.shutdown.clone();
 
-        let process_handle = tokio::spawn(async move {
-            processor.process_blocks(config_start_block).await
+        let process_handle = self.shutdown.spawn("block_source", async move {
+            let result = match (block_source, source_path) {
+                (BlockSource::Files, Some(path)) => processor.process_files(&path, config_start_block).await,
+                _ => processor.process_blocks(config_start_block).await,
+            };
+            processor_finished.store(true, Ordering::SeqCst);
+            // Once the source is done, stop the workers that would otherwise
+            // run forever alongside it.
+            processor_shutdown.trigger();
+            result
         });
 
-        let storage_handle = tokio::spawn(async move {
-            while let Ok(block) = block_receiver.recv() {
+        let storage_handle = self.shutdown.spawn("storage", async move {
+            loop {
+                // Read before polling: a finite source is done once it has
+                // stopped and everything it sent has been received.
+                let source_finished = finished.load(Ordering::SeqCst);
+                let block = match block_receiver.try_recv() {
+                    Ok(block) => block,
+                    Err(TryRecvError::Empty) if source_finished => break,
+                    Err(TryRecvError::Empty) => {
+                        // A failure stops the indexer; blocks in the open
+                        // files are recovered on the next start.
+                        storage.lock().await.flush_if_due().await?;
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => break,
+                };
+
                 metrics.record_block(&block);
-                let mut storage = storage.lock().await;
-                if let Err(e) = storage.store_block(block).await {
-                    error!("Failed to store block: {}", e);
-                    break;
-                }
+                storage.lock().await.store_block(block).await?;
             }
+
+            storage.lock().await.close().await?;
+            info!(
+                event = "storage_closed",
+                message = "Block source finished, storage files closed"
+            );
             Ok::<(), anyhow::Error>(())
         });
 
-        let handles = vec![process_handle, storage_handle];
-        
-        for result in try_join_all(handles).await? {
+        let mut handles = vec![process_handle, storage_handle];
+        handles.extend(backfill_handles);
+
+        if self.config.block_source == BlockSource::Rpc && self.config.backfill_gaps {
+            let processor = self.block_processor.clone();
+            let config = self.config.clone();
+            let indexed = self.indexed_blocks.clone();
+            let abi = self.abi.clone();
+            handles.push(self.shutdown.spawn("gap_backfill", rpc::as_backfill(async move {
+                processor.backfill_gaps(config, indexed, abi).await
+            })));
+        }
+
+        if let Some(endpoint) = self.config.mempool_ws_endpoint.clone() {
+            let limiter = rpc::limiter_for(&self.config, self.block_processor.client(), &endpoint, &self.shutdown)?;
+            let recorder = mempool::MempoolRecorder::new(&self.config, endpoint, limiter, self.shutdown.clone());
+            // Lookups give way to the head within the budget, like backfills.
+            handles.push(self.shutdown.spawn("mempool_recorder", rpc::as_backfill(recorder.run())));
+        }
+
+        let all = try_join_all(handles);
+        tokio::pin!(all);
+        let results = tokio::select! {
+            results = &mut all => results?,
+            signal = shutdown::wait_for_signal() => {
+                let deadline = Duration::from_secs(self.config.shutdown_timeout_secs);
+                info!(
+                    event = "shutdown_requested",
+                    message = "Stopping block sources and flushing storage",
+                    signal = signal?,
+                    timeout_secs = deadline.as_secs()
+                );
+                self.shutdown.trigger();
+                match tokio::time::timeout(deadline, &mut all).await {
+                    Ok(results) => results?,
+                    Err(_) => {
+                        warn!(
+                            event = "shutdown_timeout",
+                            message = "Shutdown deadline passed before files were closed",
+                            timeout_secs = deadline.as_secs()
+                        );
+                        return Err(IndexerError::StorageError(format!(
+                            "Shutdown did not finish within {}s",
+                            deadline.as_secs()
+                        ))
+                        .into());
+                    }
+                }
+            }
+        };
+        for result in results {
             result?;
         }
 
@@ -0,0 +1,233 @@
+use crate::core::gaps::IndexedBlocks;
+use crate::utils::error::IndexerError;
+use anyhow::Result;
+use arrow::array::{Array, ArrayRef, FixedSizeBinaryArray, StringArray, UInt64Array};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask},
+    file::{reader::FileReader, serialized_reader::SerializedFileReader, statistics::Statistics},
+};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+/// Where a restart without `start_block` continues: the block after the
+/// highest one stored, and the stored hash that block must build on.
+///
+/// This is not the highest *contiguous* block. Gap workers store blocks above
+/// holes, and the head follower does not skip stored blocks, so resuming at
+/// the first hole would write everything above it a second time. Instead the
+/// caller backfills the holes below the resume point, which leaves the data
+/// contiguous without duplicates.
+pub struct ResumePoint {
+    pub block: u64,
+    pub parent_hash: String,
+}
+
+/// Block range held by one row group of a blocks file.
+struct StoredRange {
+    path: PathBuf,
+    row_group: usize,
+    first: u64,
+    last: u64,
+}
+
+/// Finds the end of the stored data from `indexed_blocks.json`, or for data
+/// directories without one from the `number` statistics in blocks file
+/// footers. Holes below it are scheduled separately by the caller; see
+/// [`ResumePoint`].
+pub fn find_resume_point(data_dir: &Path, indexed: &IndexedBlocks) -> Result<Option<ResumePoint>> {
+    let ranges = stored_ranges(data_dir)?;
+
+    let mut stored = indexed.stored();
+    if stored.is_empty() && !ranges.is_empty() {
+        for range in &ranges {
+            stored.insert_range(range.first, range.last);
+        }
+        info!(
+            event = "indexed_blocks_rebuilt",
+            message = "Rebuilt indexed block ranges from blocks file footers",
+            files = ranges.len(),
+            first_block = stored.first(),
+            last_block = stored.last()
+        );
+        indexed.mark_published(&stored)?;
+    }
+
+    let Some(last) = stored.last() else {
+        return Ok(None);
+    };
+    let parent_hash = ranges.iter()
+        .filter(|range| range.first <= last && last <= range.last)
+        .find_map(|range| stored_hash(range, last).transpose())
+        .transpose()?
+        .ok_or_else(|| IndexerError::StorageError(format!("Block {} is indexed but not in any blocks file", last)))?;
+
+    Ok(Some(ResumePoint { block: last + 1, parent_hash }))
+}
+
+fn stored_ranges(data_dir: &Path) -> Result<Vec<StoredRange>> {
+    let mut ranges = Vec::new();
+    for entry in std::fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        let is_blocks_file = path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("blocks_") && name.ends_with(".parquet"));
+        if !is_blocks_file {
+            continue;
+        }
+
+        let reader = SerializedFileReader::new(File::open(&path)?)?;
+        for (row_group, metadata) in reader.metadata().row_groups().iter().enumerate() {
+            let statistics = metadata.columns().iter()
+                .find(|column| column.column_path().string() == "number")
+                .and_then(|column| column.statistics());
+            // Block numbers are unsigned, stored in a signed physical type.
+            if let Some(Statistics::Int64(statistics)) = statistics {
+                if let (Some(&min), Some(&max)) = (statistics.min_opt(), statistics.max_opt()) {
+                    ranges.push(StoredRange { path: path.clone(), row_group, first: min as u64, last: max as u64 });
+                }
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+fn stored_hash(range: &StoredRange, number: u64) -> Result<Option<String>> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&range.path)?)?;
+    let mask = ProjectionMask::columns(builder.parquet_schema(), ["number", "hash"]);
+    let reader = builder.with_row_groups(vec![range.row_group]).with_projection(mask).build()?;
+
+    for batch in reader {
+        let batch = batch?;
+        let numbers = batch.column_by_name("number").and_then(|column| column.as_any().downcast_ref::<UInt64Array>());
+        let hashes = batch.column_by_name("hash");
+        let (Some(numbers), Some(hashes)) = (numbers, hashes) else {
+            return Err(IndexerError::StorageError(format!("Unexpected blocks schema in {}", range.path.display())).into());
+        };
+        if let Some(row) = numbers.values().iter().position(|&stored| stored == number) {
+            return hash_value(hashes, row)
+                .map(Some)
+                .ok_or_else(|| IndexerError::StorageError(format!("Unexpected hash type in {}", range.path.display())).into());
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a hash written with either `column_types` setting.
+fn hash_value(column: &ArrayRef, row: usize) -> Option<String> {
+    if let Some(hashes) = column.as_any().downcast_ref::<StringArray>() {
+        return Some(hashes.value(row).to_string());
+    }
+    column.as_any()
+        .downcast_ref::<FixedSizeBinaryArray>()
+        .map(|hashes| format!("0x{}", hex::encode(hashes.value(row))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ColumnTypes, Config};
+    use crate::core::{abi::AbiRegistry, gaps::RangeSet, storage::StorageManager};
+    use crate::models::Block;
+    use std::sync::Arc;
+
+    fn data_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("resume_{}_{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn hash(number: u64) -> String {
+        format!("0x{:064x}", number + 0xb10c)
+    }
+
+    fn block(number: u64) -> Block {
+        Block {
+            number,
+            hash: hash(number),
+            parent_hash: hash(number.saturating_sub(1)),
+            transactions: Vec::new(),
+            timestamp: 1_700_000_000 + number * 12,
+            authorizations: Vec::new(),
+            uncles: Vec::new(),
+            logs: Vec::new(),
+            token_transfers: Vec::new(),
+            contracts: Vec::new(),
+            state_diffs: Vec::new(),
+            tokens: Vec::new(),
+            decoded: Vec::new(),
+        }
+    }
+
+    /// Stores each range in its own run, the way a restart or a gap worker would.
+    async fn store(dir: &Path, types: ColumnTypes, ranges: &[(u64, u64)]) {
+        let mut config = Config::from_env().unwrap();
+        config.data_dir = dir.to_path_buf();
+        config.rotation_blocks = 10;
+        config.blocks_in_memory = 2;
+        config.column_types = types;
+        for &(first, last) in ranges {
+            let indexed = Arc::new(IndexedBlocks::open(dir).unwrap());
+            let mut storage = StorageManager::new(&config, indexed, &AbiRegistry::default(), None).unwrap();
+            for number in first..=last {
+                storage.store_block(block(number)).await.unwrap();
+            }
+            storage.close().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_after_the_highest_stored_block() {
+        let dir = data_dir("highest");
+        store(&dir, ColumnTypes::Strings, &[(10, 14), (20, 22)]).await;
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        let point = find_resume_point(&dir, &indexed).unwrap().unwrap();
+        assert_eq!(point.block, 23);
+        assert_eq!(point.parent_hash, hash(22));
+        // The hole is left for the caller to backfill.
+        assert_eq!(indexed.handled().gaps(10, 22), vec![(15, 19)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rebuilds_indexed_blocks_from_file_footers() {
+        let dir = data_dir("footers");
+        // Two runs in the same window write two parts of it.
+        store(&dir, ColumnTypes::Native, &[(10, 14), (16, 17), (30, 31)]).await;
+        std::fs::remove_file(dir.join("indexed_blocks.json")).unwrap();
+
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        let point = find_resume_point(&dir, &indexed).unwrap().unwrap();
+        assert_eq!(point.block, 32);
+        // Hashes stored as binary read back in the same form.
+        assert_eq!(point.parent_hash, hash(31));
+        assert_eq!(indexed.stored().iter().collect::<Vec<_>>(), vec![(10, 14), (16, 17), (30, 31)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn starts_fresh_without_stored_blocks() {
+        let dir = data_dir("empty");
+        std::fs::create_dir_all(&dir).unwrap();
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        assert!(find_resume_point(&dir, &indexed).unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_highest_block_is_in_no_file() {
+        let dir = data_dir("missing");
+        store(&dir, ColumnTypes::Strings, &[(10, 12)]).await;
+        let indexed = IndexedBlocks::open(&dir).unwrap();
+        let mut lost = RangeSet::default();
+        lost.insert(40);
+        indexed.mark_published(&lost).unwrap();
+
+        let error = find_resume_point(&dir, &indexed).err().unwrap();
+        assert!(matches!(error.downcast_ref(), Some(IndexerError::StorageError(_))), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::error;
+
+/// Stop signal shared by the indexer's tasks. Producers check it between
+/// blocks and wake from their sleeps when it fires, so buffered blocks can
+/// still be written and files closed.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self { sender: Arc::new(watch::channel(false).0) }
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn requested(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = receiver.wait_for(|&requested| requested).await;
+    }
+
+    /// Spawns `future` and requests shutdown if it fails, so the other tasks
+    /// stop rather than run on without it.
+    pub fn spawn<F>(&self, task: &'static str, future: F) -> JoinHandle<Result<()>>
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let result = future.await;
+            if let Err(e) = &result {
+                error!(
+                    event = "task_failed",
+                    message = "Task failed, shutting down",
+                    task = task,
+                    error = %e
+                );
+                shutdown.trigger();
+            }
+            result
+        })
+    }
+
+    /// Sleeps for `duration`, returning early with `true` on shutdown.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = self.requested() => true,
+            _ = tokio::time::sleep(duration) => self.is_requested(),
+        }
+    }
+}
+
+/// Waits for SIGINT or SIGTERM and returns the signal's name.
+pub async fn wait_for_signal() -> std::io::Result<&'static str> {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+        _ = terminate.recv() => Ok("SIGTERM"),
+    }
+}
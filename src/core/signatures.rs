@@ -0,0 +1,94 @@
+use crate::models::Block;
+use crate::utils::error::IndexerError;
+use anyhow::Result;
+use metrics::counter;
+use std::{collections::HashMap, path::Path};
+use tracing::info;
+use web3::signing::keccak256;
+
+/// Signatures shipped with the indexer.
+const BUNDLED_SIGNATURES: &str = include_str!("../../config/signatures.txt");
+
+/// Text signatures keyed by their 4-byte selector and 32-byte topic hash, used
+/// to name calls and events that no loaded ABI covers.
+///
+/// Every signature is registered both as a selector and as a topic, since a
+/// plain `name(types)` line does not say whether it is a function or an event.
+#[derive(Debug, Default)]
+pub struct SignatureDb {
+    selectors: HashMap<String, String>,
+    topics: HashMap<String, String>,
+}
+
+impl SignatureDb {
+    /// Loads the bundled signatures, then `path` if given. A user file uses
+    /// the same one-signature-per-line format and wins on collisions.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut db = Self::default();
+        db.extend(BUNDLED_SIGNATURES, "bundled")?;
+
+        if let Some(path) = path {
+            let text = std::fs::read_to_string(path).map_err(|e| {
+                IndexerError::ConfigError(format!("Cannot read signatures file {}: {}", path.display(), e))
+            })?;
+            db.extend(&text, &path.display().to_string())?;
+        }
+
+        info!(
+            event = "signatures_loaded",
+            message = "Loaded signature database",
+            selectors = db.selectors.len(),
+            topics = db.topics.len()
+        );
+        Ok(db)
+    }
+
+    fn extend(&mut self, text: &str, source: &str) -> Result<()> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let signature: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+            if !signature.ends_with(')') || signature.starts_with('(') || !signature.contains('(') {
+                return Err(IndexerError::ConfigError(format!(
+                    "Invalid signature '{}' at {}:{}",
+                    line,
+                    source,
+                    index + 1
+                ))
+                .into());
+            }
+
+            let hash = keccak256(signature.as_bytes());
+            self.selectors.insert(format!("0x{}", hex::encode(&hash[..4])), signature.clone());
+            self.topics.insert(format!("0x{}", hex::encode(hash)), signature);
+        }
+        Ok(())
+    }
+
+    /// Fills in the signature of every transaction and log in `block`.
+    pub fn annotate(&self, block: &mut Block) {
+        for tx in &mut block.transactions {
+            // Plain transfers and contract creations have no selector.
+            if tx.to.is_none() {
+                continue;
+            }
+            let Some(selector) = tx.input.get(..10) else {
+                continue;
+            };
+            tx.signature = self.selectors.get(selector).cloned();
+            let result = if tx.signature.is_some() { "resolved" } else { "unknown" };
+            counter!("signatures_lookups_total", "kind" => "selector", "result" => result).increment(1);
+        }
+
+        for log in &mut block.logs {
+            let Some(topic0) = log.topics.first() else {
+                continue;
+            };
+            log.signature = self.topics.get(topic0).cloned();
+            let result = if log.signature.is_some() { "resolved" } else { "unknown" };
+            counter!("signatures_lookups_total", "kind" => "topic", "result" => result).increment(1);
+        }
+    }
+}
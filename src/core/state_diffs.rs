@@ -0,0 +1,170 @@
+use crate::models::{Block, StateDiff, StateField};
+use crate::utils::error::DecodeError;
+use serde_json::{Map, Value};
+use web3::types::U256;
+
+const ACCOUNT_FIELDS: [(&str, StateField); 3] = [
+    ("balance", StateField::Balance),
+    ("nonce", StateField::Nonce),
+    ("code", StateField::Code),
+];
+
+/// Value a storage slot holds once cleared; the prestate tracer omits such
+/// slots from `post`.
+const ZERO_SLOT: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Decodes a `trace_replayBlockTransactions` result fetched with the
+/// `stateDiff` trace type. Each field is `"="`, `{"+": new}`, `{"-": old}`
+/// or `{"*": {"from": old, "to": new}}`.
+pub fn decode_trace_state_diffs(block: &Block, replays: &Value) -> Result<Vec<StateDiff>, DecodeError> {
+    let replays = replays.as_array().ok_or_else(|| invalid("expected an array of transaction replays"))?;
+    let mut diffs = Vec::new();
+
+    for (index, replay) in replays.iter().enumerate() {
+        let transaction_hash = transaction_hash(block, replay, "transactionHash", index)?;
+        let Some(accounts) = replay.get("stateDiff").and_then(Value::as_object) else {
+            continue;
+        };
+
+        for (address, account) in accounts {
+            let mut push = |field, slot: Option<&String>, change: &Value| {
+                if let Some((from, to)) = trace_change(field, change) {
+                    diffs.push(StateDiff {
+                        block_number: block.number,
+                        transaction_hash: transaction_hash.clone(),
+                        transaction_index: index as u32,
+                        address: address.to_lowercase(),
+                        field,
+                        slot: slot.map(|slot| slot.to_lowercase()),
+                        from,
+                        to,
+                    });
+                }
+            };
+
+            for (name, field) in ACCOUNT_FIELDS {
+                if let Some(change) = account.get(name) {
+                    push(field, None, change);
+                }
+            }
+            for (slot, change) in account.get("storage").and_then(Value::as_object).into_iter().flatten() {
+                push(StateField::Storage, Some(slot), change);
+            }
+        }
+    }
+
+    Ok(diffs)
+}
+
+/// Decodes a `debug_traceBlockByNumber` result from the `prestateTracer` in
+/// diff mode, where `pre` holds the old values of modified accounts and
+/// `post` the fields that changed.
+pub fn decode_prestate_diffs(block: &Block, traces: &Value) -> Result<Vec<StateDiff>, DecodeError> {
+    let traces = traces.as_array().ok_or_else(|| invalid("expected an array of transaction traces"))?;
+    let empty = Map::new();
+    let mut diffs = Vec::new();
+
+    for (index, trace) in traces.iter().enumerate() {
+        let transaction_hash = transaction_hash(block, trace, "txHash", index)?;
+        let result = trace.get("result").ok_or(DecodeError::MissingField("trace.result"))?;
+        let pre = result.get("pre").and_then(Value::as_object).unwrap_or(&empty);
+        let post = result.get("post").and_then(Value::as_object).unwrap_or(&empty);
+
+        let mut addresses: Vec<&String> = pre.keys().chain(post.keys()).collect();
+        addresses.sort();
+        addresses.dedup();
+
+        for address in addresses {
+            let before = pre.get(address);
+            let after = post.get(address);
+            let mut push = |field, slot: Option<&String>, from: Option<&Value>, to: Option<&Value>| {
+                diffs.push(StateDiff {
+                    block_number: block.number,
+                    transaction_hash: transaction_hash.clone(),
+                    transaction_index: index as u32,
+                    address: address.to_lowercase(),
+                    field,
+                    slot: slot.map(|slot| slot.to_lowercase()),
+                    from: from.and_then(|value| state_value(field, value)),
+                    to: to.and_then(|value| state_value(field, value)),
+                });
+            };
+
+            match after {
+                Some(after) => {
+                    for (name, field) in ACCOUNT_FIELDS {
+                        if let Some(to) = after.get(name) {
+                            push(field, None, before.and_then(|before| before.get(name)), Some(to));
+                        }
+                    }
+                    let before_storage = before.and_then(|before| before.get("storage")).and_then(Value::as_object);
+                    let after_storage = after.get("storage").and_then(Value::as_object).unwrap_or(&empty);
+                    for (slot, to) in after_storage {
+                        push(StateField::Storage, Some(slot), before_storage.and_then(|s| s.get(slot)), Some(to));
+                    }
+                    for (slot, from) in before_storage.into_iter().flatten() {
+                        if !after_storage.contains_key(slot) {
+                            push(StateField::Storage, Some(slot), Some(from), Some(&Value::from(ZERO_SLOT)));
+                        }
+                    }
+                }
+                // Only in `pre`: the account was destroyed.
+                None => {
+                    let Some(before) = before else { continue };
+                    for (name, field) in ACCOUNT_FIELDS {
+                        if let Some(from) = before.get(name) {
+                            push(field, None, Some(from), None);
+                        }
+                    }
+                    for (slot, from) in before.get("storage").and_then(Value::as_object).into_iter().flatten() {
+                        push(StateField::Storage, Some(slot), Some(from), None);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(diffs)
+}
+
+/// The transaction a per-transaction trace belongs to, falling back to its
+/// position in the block for nodes that do not echo the hash.
+fn transaction_hash(block: &Block, trace: &Value, key: &str, index: usize) -> Result<String, DecodeError> {
+    trace.get(key)
+        .and_then(Value::as_str)
+        .map(str::to_lowercase)
+        .or_else(|| block.transactions.get(index).map(|tx| tx.hash.clone()))
+        .ok_or(DecodeError::MissingField("trace.transactionHash"))
+}
+
+/// `(from, to)` of a `stateDiff` field, or `None` if it is unchanged.
+fn trace_change(field: StateField, change: &Value) -> Option<(Option<String>, Option<String>)> {
+    let change = change.as_object()?;
+    if let Some(added) = change.get("+") {
+        return Some((None, state_value(field, added)));
+    }
+    if let Some(removed) = change.get("-") {
+        return Some((state_value(field, removed), None));
+    }
+    let changed = change.get("*")?;
+    Some((
+        changed.get("from").and_then(|value| state_value(field, value)),
+        changed.get("to").and_then(|value| state_value(field, value)),
+    ))
+}
+
+/// Balances and nonces as decimal, code and storage as lowercase hex.
+fn state_value(field: StateField, value: &Value) -> Option<String> {
+    match (field, value) {
+        (_, Value::Number(number)) => Some(number.to_string()),
+        (StateField::Balance | StateField::Nonce, Value::String(hex)) => {
+            U256::from_str_radix(hex.trim_start_matches("0x"), 16).ok().map(|value| value.to_string())
+        }
+        (_, Value::String(hex)) => Some(hex.to_lowercase()),
+        _ => None,
+    }
+}
+
+fn invalid(reason: &str) -> DecodeError {
+    DecodeError::InvalidField { field: "stateDiff", reason: reason.to_string() }
+}
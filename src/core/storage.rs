@@ -1,43 +1,110 @@
 use crate::config::Config;
-use crate::models::Block;
-use anyhow::Result;
-use arrow::{
-    array::{UInt64Builder, StringBuilder, ListBuilder, StructBuilder, ArrayBuilder},
-    datatypes::{Schema, Field, DataType, Fields},
-    record_batch::RecordBatch,
+use crate::core::abi::AbiRegistry;
+use crate::core::checkpoints::{self, CheckpointFile, CheckpointJournal, TEMP_SUFFIX};
+use crate::core::datasets::{
+    AbiDataset, AuthorizationsDataset, BlocksDataset, ContractsDataset, Dataset, LogsDataset, StateDiffsDataset,
+    TokenTransfersDataset, TokensDataset, UnclesDataset,
 };
+use crate::core::gaps::{IndexedBlocks, RangeSet};
+use crate::core::tokens::TokenCache;
+use crate::models::{Block, Token};
+use anyhow::Result;
 use parquet::{
     arrow::ArrowWriter,
     file::properties::WriterProperties,
 };
-use std::{fs::File, sync::Arc, path::PathBuf};
-use chrono::Utc;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+/// Rough encoded size of the fixed-width columns of one row: hashes,
+/// addresses and numbers.
+const ROW_BYTES: usize = 256;
+
+struct DatasetSink {
+    dataset: Box<dyn Dataset>,
+    /// Open writer and the temporary path it writes to.
+    writer: Option<(ArrowWriter<File>, PathBuf)>,
+    /// Row groups of the open file already in the checkpoint journal.
+    checkpointed: usize,
+}
 
+/// Writes blocks into one set of dataset files per `rotation_blocks` window.
+///
+/// Files are named after the aligned window, e.g.
+/// `blocks_000010000_000019999.parquet`, whichever of its blocks they hold.
+/// They are written under a `.parquet.tmp` name, then synced and renamed on
+/// close. A window that already has files, say after a restart in the
+/// middle of it or while the gap worker fills it in, gets another part,
+/// `blocks_000010000_000019999_1.parquet`; the part is chosen when the
+/// window opens. Every flush ends a row group and is recorded in a
+/// checkpoint journal, from which a crashed run's files are recovered on
+/// startup.
+///
+/// Buffered blocks are written once `blocks_in_memory` of them, or
+/// `flush_max_bytes` worth, have accumulated, or the oldest has waited
+/// `flush_interval_secs`, so slow trickles at the head reach disk. With `finalize_idle_secs` set, files are also
+/// closed when no block has arrived for that long, making them readable.
 pub struct StorageManager {
     data_dir: PathBuf,
     current_batch: Vec<Block>,
     batch_size: usize,
-    schema: Arc<Schema>,
-    current_writer: Option<ArrowWriter<File>>,
+    /// Estimated size of `current_batch`.
+    batch_bytes: usize,
+    /// When the oldest block in `current_batch` arrived.
+    batch_started: Option<Instant>,
+    last_block_at: Option<Instant>,
+    flush_interval: Option<Duration>,
+    flush_max_bytes: Option<usize>,
+    finalize_idle: Option<Duration>,
+    datasets: Vec<DatasetSink>,
+    rotation_blocks: u64,
+    /// Rotation window of the open files, as `block_number / rotation_blocks`.
+    window: Option<u64>,
+    /// Blocks written to the open files.
+    file_blocks: RangeSet,
+    journal: Option<CheckpointJournal>,
+    indexed: Arc<IndexedBlocks>,
+    /// Cache committed to once token rows are published.
+    tokens: Option<Arc<TokenCache>>,
+    /// Tokens written to the open files.
+    file_tokens: Vec<Token>,
 }
 
 impl StorageManager {
-    pub fn new(config: &Config) -> Result<Self> {
-        let schema = Arc::new(Schema::new(vec![
-            Field::new("number", DataType::UInt64, false),
-            Field::new("hash", DataType::Utf8, false),
-            Field::new("timestamp", DataType::UInt64, false),
-            Field::new("transactions", DataType::List(Arc::new(Field::new(
-                "transaction",
-                DataType::Struct(Fields::from(vec![
-                    Field::new("hash", DataType::Utf8, false),
-                    Field::new("from", DataType::Utf8, false),
-                    Field::new("to", DataType::Utf8, true),
-                    Field::new("value", DataType::Utf8, false),
-                ])),
-                false,
-            ))), false),
-        ]));
+    pub fn new(
+        config: &Config,
+        indexed: Arc<IndexedBlocks>,
+        abi: &AbiRegistry,
+        tokens: Option<Arc<TokenCache>>,
+    ) -> Result<Self> {
+        let mut datasets: Vec<Box<dyn Dataset>> = vec![
+            Box::new(BlocksDataset::new(config.column_types)),
+            Box::new(AuthorizationsDataset::new(config.column_types)),
+            Box::new(UnclesDataset::new(config.column_types)),
+        ];
+        if config.index_logs || !config.filter.log_emitters.is_empty() {
+            datasets.push(Box::new(LogsDataset::new(config.column_types)));
+        }
+        if config.index_token_transfers {
+            datasets.push(Box::new(TokenTransfersDataset::new(config.column_types)));
+        }
+        if config.index_tokens {
+            datasets.push(Box::new(TokensDataset::new(config.column_types)));
+        }
+        if config.index_contracts {
+            datasets.push(Box::new(ContractsDataset::new(config.column_types)));
+        }
+        if config.index_state_diffs {
+            datasets.push(Box::new(StateDiffsDataset::new(config.column_types)));
+        }
+        for table in abi.tables() {
+            datasets.push(Box::new(AbiDataset::new(table.clone(), config.column_types)));
+        }
 
         std::fs::create_dir_all(&config.data_dir)?;
 
@@ -45,135 +112,346 @@ impl StorageManager {
             data_dir: config.data_dir.clone(),
             current_batch: Vec::with_capacity(config.blocks_in_memory),
             batch_size: config.blocks_in_memory,
-            schema,
-            current_writer: None,
+            batch_bytes: 0,
+            batch_started: None,
+            last_block_at: None,
+            flush_interval: (config.flush_interval_secs > 0).then(|| Duration::from_secs(config.flush_interval_secs)),
+            flush_max_bytes: (config.flush_max_bytes > 0).then_some(config.flush_max_bytes),
+            finalize_idle: config.finalize_idle_secs.map(Duration::from_secs),
+            datasets: datasets.into_iter()
+                .map(|dataset| DatasetSink { dataset, writer: None, checkpointed: 0 })
+                .collect(),
+            rotation_blocks: config.rotation_blocks,
+            window: None,
+            file_blocks: RangeSet::default(),
+            journal: None,
+            indexed,
+            tokens,
+            file_tokens: Vec::new(),
         })
     }
 
-    fn create_new_file(&mut self) -> Result<()> {
-        let timestamp = Utc::now();
-        let filename = format!("blocks_{}.parquet", timestamp.format("%Y%m%d_%H%M%S"));
-        let path = self.data_dir.join(filename);
-        
+    fn create_writer(path: &Path, dataset: &dyn Dataset) -> Result<ArrowWriter<File>> {
         let file = File::create(path)?;
         let props = WriterProperties::builder()
             .set_compression(parquet::basic::Compression::SNAPPY)
             .build();
 
-        self.current_writer = Some(ArrowWriter::try_new(
+        Ok(ArrowWriter::try_new(
             file,
-            self.schema.clone(),
+            dataset.schema(),
             Some(props),
-        )?);
-
-        Ok(())
+        )?)
     }
 
     pub async fn store_block(&mut self, block: Block) -> Result<()> {
+        let window = block.number / self.rotation_blocks;
+        if self.window.is_some_and(|current| current != window) {
+            self.rotate_file().await?;
+        }
+        self.window = Some(window);
+        self.batch_bytes += estimated_bytes(&block);
+        self.batch_started.get_or_insert_with(Instant::now);
+        self.last_block_at = Some(Instant::now());
         self.current_batch.push(block);
 
-        if self.current_batch.len() >= self.batch_size {
+        if self.current_batch.len() >= self.batch_size
+            || self.flush_max_bytes.is_some_and(|max| self.batch_bytes >= max)
+            || self.flush_overdue()
+        {
             self.flush_batch()?;
         }
 
         Ok(())
     }
 
+    /// Writes a batch that has waited longer than the flush interval and
+    /// closes files that have been idle for `finalize_idle_secs`. Called
+    /// while no blocks are arriving.
+    pub async fn flush_if_due(&mut self) -> Result<()> {
+        if self.flush_overdue() {
+            self.flush_batch()?;
+        }
+
+        let idle = self.last_block_at.zip(self.finalize_idle)
+            .is_some_and(|(last, finalize_idle)| last.elapsed() >= finalize_idle);
+        if idle && !self.file_blocks.is_empty() {
+            info!(
+                event = "storage_finalized_idle",
+                message = "No new blocks, closing files to make them readable",
+                first_block = self.file_blocks.first(),
+                last_block = self.file_blocks.last()
+            );
+            self.close().await?;
+        }
+        Ok(())
+    }
+
+    fn flush_overdue(&self) -> bool {
+        self.batch_started.zip(self.flush_interval)
+            .is_some_and(|(started, interval)| started.elapsed() >= interval)
+    }
+
+    /// Writes buffered blocks to the open files as a row group and records
+    /// it in the checkpoint journal.
     fn flush_batch(&mut self) -> Result<()> {
         if self.current_batch.is_empty() {
             return Ok(());
         }
 
-        let data_len: usize = self.current_batch.iter()
-            .map(|block| block.transactions.len())
-            .sum();
-
-        let mut number_builder = UInt64Builder::with_capacity(self.current_batch.len());
-        let mut hash_builder = StringBuilder::with_capacity(self.current_batch.len(), self.current_batch.len() * 66);
-        let mut timestamp_builder = UInt64Builder::with_capacity(self.current_batch.len());
-
-        // Create builders for the transaction struct
-        let mut tx_hash_builder = StringBuilder::with_capacity(data_len, data_len * 66);
-        let mut tx_from_builder = StringBuilder::with_capacity(data_len, data_len * 42);
-        let mut tx_to_builder = StringBuilder::with_capacity(data_len, data_len * 42);
-        let mut tx_value_builder = StringBuilder::with_capacity(data_len, data_len * 32);
-
-        let tx_struct_builder = StructBuilder::new(
-            Fields::from(vec![
-                Field::new("hash", DataType::Utf8, false),
-                Field::new("from", DataType::Utf8, false),
-                Field::new("to", DataType::Utf8, true),
-                Field::new("value", DataType::Utf8, false),
-            ]),
-            vec![
-                Box::new(tx_hash_builder),
-                Box::new(tx_from_builder),
-                Box::new(tx_to_builder),
-                Box::new(tx_value_builder),
-            ],
-        );
-
-        let mut tx_list_builder = ListBuilder::new(tx_struct_builder);
-
+        let mut batch_blocks = RangeSet::default();
         for block in &self.current_batch {
-            number_builder.append_value(block.number);
-            hash_builder.append_value(&block.hash);
-            timestamp_builder.append_value(block.timestamp);
-            
-            let tx_list_values = tx_list_builder.values();
-            if let Some(struct_builder) = tx_list_values.as_any_mut().downcast_mut::<StructBuilder>() {
-                for tx in &block.transactions {
-                    if let Some(builder) = struct_builder.field_builder::<StringBuilder>(0) {
-                        builder.append_value(&tx.hash);
-                    }
-                    if let Some(builder) = struct_builder.field_builder::<StringBuilder>(1) {
-                        builder.append_value(&tx.from);
-                    }
-                    if let Some(builder) = struct_builder.field_builder::<StringBuilder>(2) {
-                        if let Some(to) = &tx.to {
-                            builder.append_value(to);
-                        } else {
-                            builder.append_null();
-                        }
-                    }
-                    if let Some(builder) = struct_builder.field_builder::<StringBuilder>(3) {
-                        builder.append_value(&tx.value);
-                    }
-                }
-            }
-            tx_list_builder.append(true);
+            batch_blocks.insert(block.number);
         }
+        let journal = match &mut self.journal {
+            Some(journal) => journal,
+            None => {
+                let first = self.window.unwrap_or_default().saturating_mul(self.rotation_blocks);
+                let last = first.saturating_add(self.rotation_blocks - 1);
+                self.journal.insert(open_window(&self.data_dir, &self.datasets, first, last)?)
+            }
+        };
+        self.file_blocks.extend(&batch_blocks);
 
-        let batch = RecordBatch::try_new(
-            self.schema.clone(),
-            vec![
-                Arc::new(number_builder.finish()),
-                Arc::new(hash_builder.finish()),
-                Arc::new(timestamp_builder.finish()),
-                Arc::new(tx_list_builder.finish()),
-            ],
-        )?;
+        for sink in &mut self.datasets {
+            let Some(batch) = sink.dataset.build_batch(&self.current_batch)? else {
+                continue;
+            };
 
-        if self.current_writer.is_none() {
-            self.create_new_file()?;
+            if sink.writer.is_none() {
+                let path = self.data_dir.join(format!("{}_{}{}", sink.dataset.name(), journal.key(), TEMP_SUFFIX));
+                sink.writer = Some((Self::create_writer(&path, sink.dataset.as_ref())?, path));
+            }
+
+            if let Some((writer, _)) = &mut sink.writer {
+                writer.write(&batch)?;
+                writer.flush()?;
+            }
         }
 
-        if let Some(writer) = &mut self.current_writer {
-            writer.write(&batch)?;
+        let mut files = Vec::new();
+        for sink in &mut self.datasets {
+            if let Some((writer, path)) = &sink.writer {
+                files.extend(CheckpointFile::new(path, writer, sink.checkpointed, &sink.dataset.schema())?);
+                sink.checkpointed = writer.flushed_row_groups().len();
+            }
         }
+        journal.append(&batch_blocks, files)?;
 
+        self.indexed.mark_written(self.current_batch.iter().map(|block| block.number));
+        self.file_tokens.extend(self.current_batch.iter().flat_map(|block| block.tokens.iter().cloned()));
         self.current_batch.clear();
+        self.batch_bytes = 0;
+        self.batch_started = None;
         Ok(())
     }
 
+    /// Finalizes the current window's files so the next block starts new ones.
     pub async fn rotate_file(&mut self) -> Result<()> {
+        self.close().await
+    }
+
+    /// Flushes buffered blocks, then finalizes every open file and publishes
+    /// it under the window's name.
+    pub async fn close(&mut self) -> Result<()> {
         self.flush_batch()?;
 
-        if let Some(writer) = self.current_writer.take() {
-            writer.close()?;
+        let file_blocks = std::mem::take(&mut self.file_blocks);
+        self.window = None;
+        let Some(journal) = self.journal.take() else {
+            return Ok(());
+        };
+
+        // Every file is complete on disk before any is renamed, so recovery
+        // can tell a crash mid-publication from one mid-write.
+        let mut closed = Vec::new();
+        for sink in &mut self.datasets {
+            sink.checkpointed = 0;
+            if let Some((writer, path)) = sink.writer.take() {
+                writer.close()?;
+                checkpoints::sync_file(&path)?;
+                let name = format!("{}_{}.parquet", sink.dataset.name(), journal.key());
+                closed.push((path, self.data_dir.join(name)));
+            }
         }
+        checkpoints::publish(&closed)?;
+        checkpoints::sync_dir(&self.data_dir)?;
 
-        self.create_new_file()?;
+        self.indexed.mark_published(&file_blocks)?;
+        let file_tokens = std::mem::take(&mut self.file_tokens);
+        if let Some(cache) = &self.tokens {
+            cache.publish(&file_tokens)?;
+        }
+        journal.remove()?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Reserves the names of a window's files: `<dataset>_<first>_<last>`, or
+/// with `_<part>` appended when an earlier run or another writer already
+/// has files for the window.
+fn open_window(data_dir: &Path, datasets: &[DatasetSink], first: u64, last: u64) -> Result<CheckpointJournal> {
+    let mut part = 0;
+    loop {
+        let key = match part {
+            0 => format!("{:09}_{:09}", first, last),
+            part => format!("{:09}_{:09}_{}", first, last, part),
+        };
+        if let Some(journal) = CheckpointJournal::create(data_dir, &key)? {
+            let published = datasets.iter()
+                .any(|sink| data_dir.join(format!("{}_{}.parquet", sink.dataset.name(), key)).exists());
+            if !published {
+                if part > 0 {
+                    info!(
+                        event = "window_part_opened",
+                        message = "Window already has files, writing another part",
+                        first_block = first,
+                        last_block = last,
+                        part = part
+                    );
+                }
+                return Ok(journal);
+            }
+            journal.remove()?;
+        }
+        part += 1;
+    }
+}
+
+impl Drop for StorageManager {
+    /// Releases tokens whose rows never made it into a published file.
+    fn drop(&mut self) {
+        if let Some(cache) = &self.tokens {
+            let buffered = self.current_batch.iter().flat_map(|block| &block.tokens);
+            cache.release(self.file_tokens.iter().chain(buffered).map(|token| token.address.as_str()));
+        }
+    }
+}
+
+/// Approximate encoded size of a block's rows across all datasets.
+fn estimated_bytes(block: &Block) -> usize {
+    let rows = 1
+        + block.transactions.len()
+        + block.authorizations.len()
+        + block.uncles.len()
+        + block.logs.len()
+        + block.token_transfers.len()
+        + block.contracts.len()
+        + block.state_diffs.len()
+        + block.tokens.len()
+        + block.decoded.len();
+    let variable: usize = block.transactions.iter().map(|tx| tx.input.len()).sum::<usize>()
+        + block.logs.iter().map(|log| log.data.len()).sum::<usize>()
+        + block.contracts.iter().map(|contract| contract.code.len()).sum::<usize>();
+    rows * ROW_BYTES + variable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::UInt64Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn config(test: &str) -> Config {
+        let mut config = Config::from_env().unwrap();
+        config.data_dir = std::env::temp_dir().join(format!("storage_{}_{}", test, std::process::id()));
+        config.rotation_blocks = 10;
+        config.blocks_in_memory = 2;
+        let _ = std::fs::remove_dir_all(&config.data_dir);
+        config
+    }
+
+    fn storage(config: &Config) -> StorageManager {
+        let indexed = Arc::new(IndexedBlocks::open(&config.data_dir).unwrap());
+        StorageManager::new(config, indexed, &AbiRegistry::default(), None).unwrap()
+    }
+
+    fn block(number: u64) -> Block {
+        Block {
+            number,
+            hash: format!("0x{:064x}", number),
+            parent_hash: format!("0x{:064x}", number.saturating_sub(1)),
+            transactions: Vec::new(),
+            timestamp: 1_700_000_000 + number * 12,
+            authorizations: Vec::new(),
+            uncles: Vec::new(),
+            logs: Vec::new(),
+            token_transfers: Vec::new(),
+            contracts: Vec::new(),
+            state_diffs: Vec::new(),
+            tokens: Vec::new(),
+            decoded: Vec::new(),
+        }
+    }
+
+    async fn store(storage: &mut StorageManager, numbers: impl IntoIterator<Item = u64>) {
+        for number in numbers {
+            storage.store_block(block(number)).await.unwrap();
+        }
+    }
+
+    fn blocks_files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with("blocks_") || name.ends_with(TEMP_SUFFIX) || name.starts_with("window_"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn numbers(path: &Path) -> Vec<u64> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+        reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let numbers = batch.column_by_name("number").unwrap().as_any().downcast_ref::<UInt64Array>().unwrap();
+                numbers.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rotates_into_files_named_after_the_window() {
+        let config = config("rotation");
+        let mut storage = storage(&config);
+        store(&mut storage, 13..=24).await;
+        storage.close().await.unwrap();
+
+        let dir = &config.data_dir;
+        assert_eq!(blocks_files(dir), vec![
+            "blocks_000000010_000000019.parquet",
+            "blocks_000000020_000000029.parquet",
+        ]);
+        assert_eq!(numbers(&dir.join("blocks_000000010_000000019.parquet")), (13..=19).collect::<Vec<_>>());
+        assert_eq!(numbers(&dir.join("blocks_000000020_000000029.parquet")), (20..=24).collect::<Vec<_>>());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn writes_another_part_of_a_window_that_has_files() {
+        let config = config("parts");
+        let mut first = storage(&config);
+        store(&mut first, 20..=21).await;
+        // A second writer in the same window, like a gap worker, cannot take
+        // the name the first one holds.
+        let mut second = storage(&config);
+        store(&mut second, 24..=25).await;
+        first.close().await.unwrap();
+        second.close().await.unwrap();
+        // Nor can a later run take either published name.
+        let mut third = storage(&config);
+        store(&mut third, 26..=27).await;
+        third.close().await.unwrap();
+
+        let dir = &config.data_dir;
+        assert_eq!(blocks_files(dir), vec![
+            "blocks_000000020_000000029.parquet",
+            "blocks_000000020_000000029_1.parquet",
+            "blocks_000000020_000000029_2.parquet",
+        ]);
+        assert_eq!(numbers(&dir.join("blocks_000000020_000000029.parquet")), vec![20, 21]);
+        assert_eq!(numbers(&dir.join("blocks_000000020_000000029_1.parquet")), vec![24, 25]);
+        assert_eq!(numbers(&dir.join("blocks_000000020_000000029_2.parquet")), vec![26, 27]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
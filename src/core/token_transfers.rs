@@ -0,0 +1,269 @@
+use crate::models::{Log, TokenStandard, TokenTransfer};
+use metrics::counter;
+use web3::{
+    ethabi::{self, ParamType, Token},
+    types::U256,
+};
+
+/// `Transfer(address,address,uint256)`, shared by ERC-20 and ERC-721.
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+/// `TransferSingle(address,address,address,uint256,uint256)`
+const TRANSFER_SINGLE_TOPIC: &str = "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+/// `TransferBatch(address,address,address,uint256[],uint256[])`
+const TRANSFER_BATCH_TOPIC: &str = "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+
+/// Decodes ERC-20, ERC-721 and ERC-1155 transfer events. Logs that carry a
+/// transfer topic but do not fit its layout are counted and skipped.
+pub fn decode_token_transfers(logs: &[Log]) -> Vec<TokenTransfer> {
+    let mut transfers = Vec::new();
+
+    for log in logs {
+        let decoded = match log.topics.first().map(String::as_str) {
+            Some(TRANSFER_TOPIC) => decode_transfer(log),
+            Some(TRANSFER_SINGLE_TOPIC) => decode_transfer_single(log),
+            Some(TRANSFER_BATCH_TOPIC) => decode_transfer_batch(log),
+            _ => continue,
+        };
+
+        match decoded {
+            Some(decoded) => transfers.extend(decoded),
+            None => counter!("token_transfers_undecodable_total").increment(1),
+        }
+    }
+
+    transfers
+}
+
+fn decode_transfer(log: &Log) -> Option<Vec<TokenTransfer>> {
+    let data = hex_data(log)?;
+    // ERC-721 indexes the token id, ERC-20 puts the amount in data.
+    let (standard, token_id, amount) = match (log.topics.len(), data.len()) {
+        (3, 32) => (TokenStandard::Erc20, None, U256::from_big_endian(&data)),
+        (4, 0) => (TokenStandard::Erc721, Some(topic_uint(&log.topics[3])?), U256::one()),
+        _ => return None,
+    };
+
+    Some(vec![transfer(
+        log,
+        None,
+        standard,
+        None,
+        topic_address(&log.topics[1])?,
+        topic_address(&log.topics[2])?,
+        token_id,
+        amount,
+    )])
+}
+
+fn decode_transfer_single(log: &Log) -> Option<Vec<TokenTransfer>> {
+    if log.topics.len() != 4 {
+        return None;
+    }
+    let data = hex_data(log)?;
+    if data.len() != 64 {
+        return None;
+    }
+
+    Some(vec![transfer(
+        log,
+        None,
+        TokenStandard::Erc1155,
+        Some(topic_address(&log.topics[1])?),
+        topic_address(&log.topics[2])?,
+        topic_address(&log.topics[3])?,
+        Some(U256::from_big_endian(&data[..32])),
+        U256::from_big_endian(&data[32..]),
+    )])
+}
+
+fn decode_transfer_batch(log: &Log) -> Option<Vec<TokenTransfer>> {
+    if log.topics.len() != 4 {
+        return None;
+    }
+    let data = hex_data(log)?;
+    let array = ParamType::Array(Box::new(ParamType::Uint(256)));
+    let tokens = ethabi::decode(&[array.clone(), array], &data).ok()?;
+    let (Some(Token::Array(ids)), Some(Token::Array(values))) = (tokens.first(), tokens.get(1)) else {
+        return None;
+    };
+    if ids.len() != values.len() {
+        return None;
+    }
+
+    let operator = topic_address(&log.topics[1])?;
+    let from = topic_address(&log.topics[2])?;
+    let to = topic_address(&log.topics[3])?;
+
+    ids.iter()
+        .zip(values)
+        .enumerate()
+        .map(|(index, (id, value))| {
+            Some(transfer(
+                log,
+                Some(index as u32),
+                TokenStandard::Erc1155,
+                Some(operator.clone()),
+                from.clone(),
+                to.clone(),
+                Some(id.clone().into_uint()?),
+                value.clone().into_uint()?,
+            ))
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn transfer(
+    log: &Log,
+    batch_index: Option<u32>,
+    standard: TokenStandard,
+    operator: Option<String>,
+    from: String,
+    to: String,
+    token_id: Option<U256>,
+    amount: U256,
+) -> TokenTransfer {
+    TokenTransfer {
+        block_number: log.block_number,
+        transaction_hash: log.transaction_hash.clone(),
+        log_index: log.log_index,
+        batch_index,
+        token_address: log.address.clone(),
+        standard,
+        operator,
+        from,
+        to,
+        token_id: token_id.map(|id| id.to_string()),
+        amount: amount.to_string(),
+    }
+}
+
+fn hex_data(log: &Log) -> Option<Vec<u8>> {
+    hex::decode(log.data.trim_start_matches("0x")).ok()
+}
+
+/// Address held in the low 20 bytes of an indexed topic.
+fn topic_address(topic: &str) -> Option<String> {
+    let hex = topic.trim_start_matches("0x");
+    (hex.len() == 64).then(|| format!("0x{}", &hex[24..]))
+}
+
+fn topic_uint(topic: &str) -> Option<U256> {
+    let bytes = hex::decode(topic.trim_start_matches("0x")).ok()?;
+    (bytes.len() == 32).then(|| U256::from_big_endian(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const ALICE: &str = "0x1111111111111111111111111111111111111111";
+    const BOB: &str = "0x2222222222222222222222222222222222222222";
+    const OPERATOR: &str = "0x3333333333333333333333333333333333333333";
+
+    fn word(value: u64) -> String {
+        format!("{:064x}", value)
+    }
+
+    fn address_topic(address: &str) -> String {
+        format!("0x{:0>64}", address.trim_start_matches("0x"))
+    }
+
+    fn log(topics: &[&str], data: &str) -> Log {
+        Log {
+            block_number: 100,
+            transaction_hash: format!("0x{}", "ab".repeat(32)),
+            transaction_index: 0,
+            log_index: 7,
+            address: TOKEN.to_string(),
+            topics: topics.iter().map(|topic| topic.to_string()).collect(),
+            data: data.to_string(),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn decodes_erc20_transfer() {
+        let (from, to) = (address_topic(ALICE), address_topic(BOB));
+        // 1 USDC with 6 decimals.
+        let transfers = decode_token_transfers(&[log(&[TRANSFER_TOPIC, &from, &to], &format!("0x{}", word(1_000_000)))]);
+
+        assert_eq!(transfers.len(), 1);
+        let transfer = &transfers[0];
+        assert_eq!(transfer.standard, TokenStandard::Erc20);
+        assert_eq!((transfer.from.as_str(), transfer.to.as_str()), (ALICE, BOB));
+        assert_eq!(transfer.token_address, TOKEN);
+        assert_eq!(transfer.token_id, None);
+        assert_eq!(transfer.amount, "1000000");
+        assert_eq!((transfer.block_number, transfer.log_index, transfer.batch_index), (100, 7, None));
+    }
+
+    #[test]
+    fn decodes_erc721_transfer() {
+        let (from, to, id) = (address_topic(ALICE), address_topic(BOB), format!("0x{}", word(42)));
+        let transfers = decode_token_transfers(&[log(&[TRANSFER_TOPIC, &from, &to, &id], "0x")]);
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].standard, TokenStandard::Erc721);
+        assert_eq!(transfers[0].token_id.as_deref(), Some("42"));
+        assert_eq!(transfers[0].amount, "1");
+    }
+
+    #[test]
+    fn decodes_erc1155_single_and_batch_transfers() {
+        let (operator, from, to) = (address_topic(OPERATOR), address_topic(ALICE), address_topic(BOB));
+        let single = log(&[TRANSFER_SINGLE_TOPIC, &operator, &from, &to], &format!("0x{}{}", word(5), word(3)));
+        // ids [1, 2] and values [10, 20], each array behind an offset.
+        let batch_data = format!(
+            "0x{}{}{}{}{}{}{}{}",
+            word(0x40), word(0xa0), word(2), word(1), word(2), word(2), word(10), word(20)
+        );
+        let batch = log(&[TRANSFER_BATCH_TOPIC, &operator, &from, &to], &batch_data);
+
+        let transfers = decode_token_transfers(&[single, batch]);
+        assert_eq!(transfers.len(), 3);
+        assert!(transfers.iter().all(|transfer| transfer.standard == TokenStandard::Erc1155
+            && transfer.operator.as_deref() == Some(OPERATOR)));
+        let rows: Vec<_> = transfers.iter()
+            .map(|transfer| (transfer.batch_index, transfer.token_id.clone().unwrap(), transfer.amount.clone()))
+            .collect();
+        assert_eq!(rows, vec![
+            (None, "5".to_string(), "3".to_string()),
+            (Some(0), "1".to_string(), "10".to_string()),
+            (Some(1), "2".to_string(), "20".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn skips_logs_that_do_not_fit_their_transfer_layout() {
+        let (operator, from, to) = (address_topic(OPERATOR), address_topic(ALICE), address_topic(BOB));
+        let amount = format!("0x{}", word(1));
+        let malformed = [
+            // Too few topics for either ERC-20 or ERC-721.
+            log(&[TRANSFER_TOPIC, &from], &amount),
+            // ERC-20 amount cut short.
+            log(&[TRANSFER_TOPIC, &from, &to], "0x01"),
+            // Address topic of the wrong length.
+            log(&[TRANSFER_TOPIC, "0x1111", &to], &amount),
+            // Data that is not hex.
+            log(&[TRANSFER_TOPIC, &from, &to], "0xzz"),
+            // TransferSingle without the operator topic.
+            log(&[TRANSFER_SINGLE_TOPIC, &from, &to], &format!("0x{}{}", word(5), word(3))),
+            // TransferBatch whose arrays differ in length.
+            log(
+                &[TRANSFER_BATCH_TOPIC, &operator, &from, &to],
+                &format!("0x{}{}{}{}{}{}{}", word(0x40), word(0xa0), word(2), word(1), word(2), word(1), word(10)),
+            ),
+        ];
+        let unrelated = log(&["0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925", &from, &to], &amount);
+        let valid = log(&[TRANSFER_TOPIC, &from, &to], &amount);
+
+        let mut logs = malformed.to_vec();
+        logs.push(unrelated);
+        logs.push(valid);
+        let transfers = decode_token_transfers(&logs);
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].amount, "1");
+    }
+}
@@ -0,0 +1,124 @@
+use crate::models::Token;
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+};
+use tracing::info;
+use web3::{
+    ethabi::{decode, ParamType, Token as AbiToken},
+    types::U256,
+};
+
+/// Name of the file in `data_dir` caching token metadata.
+const TOKEN_CACHE_FILE: &str = "token_metadata.jsonl";
+
+pub const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+pub const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+pub const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+pub const TOTAL_SUPPLY_SELECTOR: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd];
+
+/// Token contracts whose metadata has already been read, backed by an
+/// append-only `token_metadata.jsonl` in `data_dir` so restarts do not call
+/// them again.
+///
+/// A token is claimed when its first transfer is fetched and only written to
+/// the file once the files holding its row are published, so a row lost
+/// with a failed or crashed write is produced again when the block is.
+pub struct TokenCache {
+    known: Mutex<HashSet<String>>,
+    /// Tokens whose rows are on their way to storage.
+    claimed: Mutex<HashSet<String>>,
+    file: Mutex<File>,
+}
+
+impl TokenCache {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(TOKEN_CACHE_FILE);
+
+        let mut known = HashSet::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    // A line cut short by a crash is read again on discovery.
+                    if let Ok(token) = serde_json::from_str::<Token>(&line) {
+                        known.insert(token.address);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        info!(
+            event = "token_cache_loaded",
+            message = "Loaded token metadata cache",
+            tokens = known.len()
+        );
+
+        Ok(Self {
+            known: Mutex::new(known),
+            claimed: Mutex::new(HashSet::new()),
+            file: Mutex::new(OpenOptions::new().create(true).append(true).open(path)?),
+        })
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.known.lock().unwrap().contains(address) || self.claimed.lock().unwrap().contains(address)
+    }
+
+    /// Reserves `address` for the caller's block, returning `false` if it is
+    /// known or another task got there first.
+    pub fn claim(&self, address: &str) -> bool {
+        let known = self.known.lock().unwrap();
+        !known.contains(address) && self.claimed.lock().unwrap().insert(address.to_string())
+    }
+
+    /// Records tokens whose rows are now in published files.
+    pub fn publish(&self, tokens: &[Token]) -> Result<()> {
+        let mut known = self.known.lock().unwrap();
+        let mut file = self.file.lock().unwrap();
+        for token in tokens {
+            let mut line = serde_json::to_vec(token)?;
+            line.push(b'\n');
+            file.write_all(&line)?;
+            known.insert(token.address.clone());
+            self.claimed.lock().unwrap().remove(&token.address);
+        }
+        Ok(())
+    }
+
+    /// Gives up claims whose rows were never published, so the next block
+    /// transferring these tokens reads their metadata again.
+    pub fn release<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) {
+        let mut claimed = self.claimed.lock().unwrap();
+        for address in addresses {
+            claimed.remove(address);
+        }
+    }
+}
+
+/// A `string` return value, or a `bytes32` one as used by early tokens such
+/// as MKR, with trailing padding removed.
+pub fn decode_text(output: &[u8]) -> Option<String> {
+    let text = match decode(&[ParamType::String], output).ok().as_deref() {
+        Some([AbiToken::String(text)]) => text.clone(),
+        _ if output.len() == 32 => String::from_utf8_lossy(output).into_owned(),
+        _ => return None,
+    };
+    let text = text.trim_matches(char::from(0)).trim().to_string();
+    (!text.is_empty() && !text.contains(char::from(0))).then_some(text)
+}
+
+pub fn decode_uint(output: &[u8]) -> Option<U256> {
+    (output.len() >= 32).then(|| U256::from_big_endian(&output[..32]))
+}
+
+/// `decimals()` is a `uint8`, but some tokens return garbage from a fallback.
+pub fn decode_decimals(output: &[u8]) -> Option<u8> {
+    decode_uint(output).and_then(|value| u8::try_from(value).ok())
+}
@@ -8,6 +8,7 @@ static GLOBAL: MiMalloc = MiMalloc;
 mod config;
 mod core;
 mod models;
+mod rpc;
 mod utils;
 
 use crate::core::Indexer;
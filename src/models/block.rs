@@ -4,8 +4,23 @@ use serde::{Deserialize, Serialize};
 pub struct Block {
     pub number: u64,
     pub hash: String,
+    pub parent_hash: String,
     pub transactions: Vec<Transaction>,
     pub timestamp: u64,
+    pub authorizations: Vec<Authorization>,
+    pub uncles: Vec<Uncle>,
+    /// Logs from the block's receipts; empty unless receipts were fetched.
+    pub logs: Vec<Log>,
+    /// Token movements decoded from `logs`.
+    pub token_transfers: Vec<TokenTransfer>,
+    /// Contracts deployed in the block, with their runtime code.
+    pub contracts: Vec<Contract>,
+    /// Account changes made by each transaction.
+    pub state_diffs: Vec<StateDiff>,
+    /// Token contracts first seen in this block's transfers.
+    pub tokens: Vec<Token>,
+    /// Logs and calls decoded with user-supplied ABIs.
+    pub decoded: Vec<DecodedRow>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,4 +29,190 @@ pub struct Transaction {
     pub from: String,
     pub to: Option<String>,
     pub value: String,
-}
\ No newline at end of file
+    /// Calldata as 0x-prefixed hex.
+    pub input: String,
+    /// Text signature of the called function, from the signature database.
+    pub signature: Option<String>,
+}
+
+/// An event log emitted by a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Log {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub transaction_index: u32,
+    pub log_index: u32,
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+    /// Text signature of the event, from the signature database.
+    pub signature: Option<String>,
+}
+
+/// An EIP-7702 authorization tuple carried by a type-4 (set-code) transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Authorization {
+    pub block_number: u64,
+    pub tx_hash: String,
+    pub index: u32,
+    /// Decimal; any uint256 is allowed, with 0 meaning every chain.
+    pub chain_id: String,
+    pub address: String,
+    pub nonce: u64,
+    /// Authority recovered from the tuple signature; `None` when the
+    /// signature is invalid, in which case the EVM skips the tuple.
+    pub signer: Option<String>,
+}
+
+/// Header of an uncle (ommer) referenced by a pre-merge block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Uncle {
+    pub block_number: u64,
+    pub uncle_index: u32,
+    pub number: u64,
+    pub hash: String,
+    pub parent_hash: String,
+    pub miner: String,
+    pub timestamp: u64,
+    pub difficulty: String,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+}
+
+/// Token standard a transfer event belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenStandard {
+    Erc20,
+    Erc721,
+    Erc1155,
+}
+
+impl TokenStandard {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Erc20 => "erc20",
+            Self::Erc721 => "erc721",
+            Self::Erc1155 => "erc1155",
+        }
+    }
+}
+
+/// Metadata of a token contract, read when it is first seen. Each field is
+/// `None` if the contract does not implement the getter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Token {
+    pub address: String,
+    pub standard: TokenStandard,
+    /// Block the token was discovered in and the metadata was read at.
+    pub block_number: u64,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    /// Decimal 256-bit total supply.
+    pub total_supply: Option<String>,
+}
+
+/// One token movement. ERC-1155 batch transfers produce a row per id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenTransfer {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub log_index: u32,
+    /// Position within an ERC-1155 `TransferBatch`; `None` for other events.
+    pub batch_index: Option<u32>,
+    pub token_address: String,
+    pub standard: TokenStandard,
+    /// ERC-1155 operator; `None` for ERC-20 and ERC-721.
+    pub operator: Option<String>,
+    pub from: String,
+    pub to: String,
+    /// Decimal token id; `None` for ERC-20.
+    pub token_id: Option<String>,
+    /// Decimal 256-bit amount; always 1 for ERC-721.
+    pub amount: String,
+}
+
+/// A contract deployed by a transaction, either directly or by an internal
+/// `CREATE`/`CREATE2`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contract {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    /// Account that executed the creation: the transaction sender for
+    /// top-level deployments, the factory contract for internal ones.
+    pub creator: String,
+    pub address: String,
+    /// Created by an internal call rather than the transaction itself.
+    pub internal: bool,
+    /// Runtime bytecode at the end of the block, as 0x-prefixed hex.
+    pub code: String,
+    /// Keccak-256 of the runtime bytecode.
+    pub code_hash: String,
+}
+
+/// Account field changed by a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StateField {
+    Balance,
+    Nonce,
+    Code,
+    Storage,
+}
+
+impl StateField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Balance => "balance",
+            Self::Nonce => "nonce",
+            Self::Code => "code",
+            Self::Storage => "storage",
+        }
+    }
+}
+
+/// One account field changed by a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub transaction_index: u32,
+    pub address: String,
+    pub field: StateField,
+    /// Storage slot, for storage changes only.
+    pub slot: Option<String>,
+    /// Value before the transaction; `None` if the account was created.
+    /// Balances and nonces are decimal, code and storage 0x-prefixed hex.
+    pub from: Option<String>,
+    /// Value after the transaction; `None` if the account was deleted.
+    pub to: Option<String>,
+}
+
+/// A value decoded from an ABI parameter, already in the form its column
+/// stores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DecodedValue {
+    UInt(u64),
+    Int(i64),
+    Bool(bool),
+    /// Addresses, byte strings as hex, integers wider than 64 bits as
+    /// decimal, and arrays/tuples as JSON.
+    Text(String),
+}
+
+/// One event or call decoded with an ABI, destined for the table `table`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedRow {
+    pub table: String,
+    pub block_number: u64,
+    pub transaction_hash: String,
+    /// Log index for events; `None` for calls.
+    pub log_index: Option<u32>,
+    /// Emitting or called contract.
+    pub address: String,
+    /// Caller, for calls only.
+    pub from: Option<String>,
+    /// Wei sent with the call, for calls only.
+    pub value: Option<String>,
+    /// One value per ABI parameter, in ABI order.
+    pub values: Vec<DecodedValue>,
+}
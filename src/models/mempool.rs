@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// A transaction seen in the mempool, recorded the first time it arrives.
+/// Fields other than `hash` are `None` when the node only announces hashes
+/// and the transaction could not be fetched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingTransaction {
+    pub hash: String,
+    /// Unix time in milliseconds when the transaction was first received.
+    pub first_seen_ms: u64,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub nonce: Option<u64>,
+    pub value: Option<String>,
+    pub gas: Option<u64>,
+    pub gas_price: Option<String>,
+    pub max_fee_per_gas: Option<String>,
+    pub max_priority_fee_per_gas: Option<String>,
+    /// Calldata as 0x-prefixed hex.
+    pub input: Option<String>,
+}
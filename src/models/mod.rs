@@ -1,2 +1,7 @@
 mod block;
-pub use block::{Block, Transaction};
\ No newline at end of file
+mod mempool;
+pub use block::{
+    Authorization, Block, Contract, DecodedRow, DecodedValue, Log, StateDiff, StateField, Token,
+    TokenStandard, TokenTransfer, Transaction, Uncle,
+};
+pub use mempool::PendingTransaction;
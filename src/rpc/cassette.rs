@@ -0,0 +1,211 @@
+use anyhow::Result;
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use futures::future::BoxFuture;
+use jsonrpc_core::{Call, Value};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing::{error, info};
+use web3::{error::TransportError, helpers, RequestId, Transport};
+
+/// One recorded JSON-RPC exchange.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    method: String,
+    params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<jsonrpc_core::Error>,
+}
+
+fn exchange_key(method: &str, params: &Value) -> String {
+    format!("{}:{}", method, params)
+}
+
+fn call_parts(request: &Call) -> (String, Value) {
+    match request {
+        Call::MethodCall(call) => (call.method.clone(), serde_json::to_value(&call.params).unwrap_or(Value::Null)),
+        Call::Notification(notification) => {
+            (notification.method.clone(), serde_json::to_value(&notification.params).unwrap_or(Value::Null))
+        }
+        Call::Invalid { .. } => (String::new(), Value::Null),
+    }
+}
+
+/// Appends exchanges to a cassette. Every exchange is written as its own gzip
+/// member, so the file stays readable even if the process dies mid-run.
+#[derive(Debug)]
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        info!(
+            event = "cassette_recording",
+            message = "Recording RPC exchanges",
+            path = %path.display()
+        );
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    fn record(&self, exchange: &Exchange) -> Result<()> {
+        let mut line = serde_json::to_vec(exchange)?;
+        line.push(b'\n');
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&line)?;
+        let member = encoder.finish()?;
+
+        self.file.lock().unwrap().write_all(&member)?;
+        Ok(())
+    }
+}
+
+/// Responses loaded from a cassette, served in recording order per request.
+/// Once a request's recorded responses run out, the last one keeps being
+/// returned so polling calls such as `eth_blockNumber` settle on the final head.
+#[derive(Debug)]
+pub struct Cassette {
+    responses: Mutex<HashMap<String, VecDeque<Exchange>>>,
+    next_id: AtomicUsize,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let reader = BufReader::new(MultiGzDecoder::new(File::open(path)?));
+        let mut responses: HashMap<String, VecDeque<Exchange>> = HashMap::new();
+        let mut count = 0usize;
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange: Exchange = serde_json::from_str(&line)?;
+            responses.entry(exchange_key(&exchange.method, &exchange.params))
+                .or_default()
+                .push_back(exchange);
+            count += 1;
+        }
+
+        info!(
+            event = "cassette_loaded",
+            message = "Replaying RPC exchanges from cassette",
+            path = %path.display(),
+            exchanges = count
+        );
+
+        Ok(Self {
+            responses: Mutex::new(responses),
+            next_id: AtomicUsize::new(1),
+        })
+    }
+
+    fn replay(&self, method: &str, params: &Value) -> web3::error::Result<Value> {
+        let key = exchange_key(method, params);
+        let mut responses = self.responses.lock().unwrap();
+        let queue = responses.get_mut(&key).ok_or_else(|| {
+            web3::Error::Transport(TransportError::Message(format!("Request not in cassette: {}", key)))
+        })?;
+
+        let exchange = if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        };
+
+        match exchange {
+            Some(Exchange { error: Some(error), .. }) => Err(web3::Error::Rpc(error)),
+            Some(Exchange { result, .. }) => Ok(result.unwrap_or(Value::Null)),
+            None => Err(web3::Error::Transport(TransportError::Message(format!("Empty cassette entry: {}", key)))),
+        }
+    }
+}
+
+/// Outermost transport layer: passes requests through, additionally records
+/// them, or serves them from a cassette without touching the network.
+#[derive(Debug, Clone)]
+pub enum CassetteTransport<T> {
+    Live(T),
+    Record(T, Arc<Recorder>),
+    Replay(Arc<Cassette>),
+}
+
+impl<T> CassetteTransport<T> {
+    /// The transport requests go out on; `None` when replaying.
+    pub fn upstream(&self) -> Option<&T> {
+        match self {
+            Self::Live(inner) | Self::Record(inner, _) => Some(inner),
+            Self::Replay(_) => None,
+        }
+    }
+}
+
+impl<T> Transport for CassetteTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, web3::error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        match self {
+            Self::Live(inner) | Self::Record(inner, _) => inner.prepare(method, params),
+            Self::Replay(cassette) => {
+                let id = cassette.next_id.fetch_add(1, Ordering::Relaxed);
+                (id, helpers::build_request(id, method, params))
+            }
+        }
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        match self {
+            Self::Live(inner) => Box::pin(inner.send(id, request)),
+            Self::Record(inner, recorder) => {
+                let (method, params) = call_parts(&request);
+                let out = inner.send(id, request);
+                let recorder = recorder.clone();
+
+                Box::pin(async move {
+                    let result = out.await;
+                    // Transport failures are not node answers; only results
+                    // and JSON-RPC errors are worth replaying.
+                    let exchange = match &result {
+                        Ok(value) => Some(Exchange { method, params, result: Some(value.clone()), error: None }),
+                        Err(web3::Error::Rpc(e)) => Some(Exchange { method, params, result: None, error: Some(e.clone()) }),
+                        Err(_) => None,
+                    };
+                    if let Some(exchange) = exchange {
+                        if let Err(e) = recorder.record(&exchange) {
+                            error!(
+                                event = "cassette_write_error",
+                                message = "Failed to record RPC exchange",
+                                error = %e
+                            );
+                        }
+                    }
+                    result
+                })
+            }
+            Self::Replay(cassette) => {
+                let (method, params) = call_parts(&request);
+                let result = cassette.replay(&method, &params);
+                Box::pin(async move { result })
+            }
+        }
+    }
+}
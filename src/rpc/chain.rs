@@ -0,0 +1,170 @@
+use super::RpcTransport;
+use crate::config::Config;
+use crate::utils::error::IndexerError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+use web3::{
+    types::{BlockId, BlockNumber, U256},
+    Transport, Web3,
+};
+
+/// Name of the file in `data_dir` recording which chain the data belongs to.
+const CHAIN_METADATA_FILE: &str = "chain.json";
+
+/// Identifies a network by its chain id and genesis block hash. The chain id
+/// alone is not enough: forks and devnets reuse ids.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainIdentity {
+    pub chain_id: u64,
+    pub genesis_hash: String,
+}
+
+impl std::fmt::Display for ChainIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chain id {} (genesis {})", self.chain_id, self.genesis_hash)
+    }
+}
+
+async fn fetch_identity<T: Transport>(client: &Web3<T>) -> Result<ChainIdentity> {
+    let chain_id = client.eth().chain_id().await
+        .map_err(|e| IndexerError::RpcError(format!("eth_chainId failed: {}", e)))?;
+    if chain_id > U256::from(u64::MAX) {
+        return Err(IndexerError::RpcError(format!("eth_chainId returned {}, which does not fit in 64 bits", chain_id)).into());
+    }
+    let genesis = client.eth().block(BlockId::Number(BlockNumber::Number(0.into()))).await
+        .map_err(|e| IndexerError::RpcError(format!("Failed to fetch genesis block: {}", e)))?
+        .ok_or_else(|| IndexerError::RpcError("Genesis block not found".into()))?;
+    let genesis_hash = genesis.hash
+        .ok_or_else(|| IndexerError::RpcError("Genesis block has no hash".into()))?;
+
+    Ok(ChainIdentity {
+        chain_id: chain_id.as_u64(),
+        genesis_hash: format!("{:?}", genesis_hash),
+    })
+}
+
+/// Checks that every configured endpoint serves the same chain and that it
+/// matches the chain recorded in `data_dir`. The first run records it.
+///
+/// Only the RPC block source is checked: dump files read in `files` mode
+/// carry no chain identity, so which chain they belong to is up to the
+/// operator.
+pub async fn verify_chain(config: &Config, client: &Web3<RpcTransport>) -> Result<ChainIdentity> {
+    // Queried through the full stack so record and replay modes see it too.
+    let identity = fetch_identity(client).await?;
+
+    // Each endpoint is asked through its own rate limiter, so these calls
+    // count against its budget like any other.
+    if let Some(hedged) = client.transport().upstream() {
+        for (label, transport) in hedged.endpoints() {
+            let endpoint_identity = fetch_identity(&Web3::new(transport.clone())).await?;
+            if endpoint_identity != identity {
+                return Err(IndexerError::ChainMismatch(format!(
+                    "endpoint {} serves {}, expected {}",
+                    label, endpoint_identity, identity
+                )).into());
+            }
+        }
+    }
+
+    let path = config.data_dir.join(CHAIN_METADATA_FILE);
+    match read_metadata(&path)? {
+        Some(expected) if expected != identity => {
+            return Err(IndexerError::ChainMismatch(format!(
+                "{} was written for {}, but the RPC endpoints serve {}",
+                path.display(), expected, identity
+            )).into());
+        }
+        Some(_) => {}
+        None => {
+            std::fs::create_dir_all(&config.data_dir)?;
+            std::fs::write(&path, serde_json::to_vec_pretty(&identity)?)?;
+            info!(
+                event = "chain_metadata_written",
+                message = "Recorded chain identity for data directory",
+                path = %path.display()
+            );
+        }
+    }
+
+    info!(
+        event = "chain_verified",
+        message = "RPC endpoints match the data directory chain",
+        chain_id = identity.chain_id,
+        genesis_hash = %identity.genesis_hash
+    );
+
+    Ok(identity)
+}
+
+fn read_metadata(path: &Path) -> Result<Option<ChainIdentity>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::{ready, Ready};
+    use jsonrpc_core::{Call, Value};
+    use serde_json::json;
+    use web3::RequestId;
+
+    /// Answers `eth_chainId` with `chain_id` and any block query with a genesis block.
+    #[derive(Debug, Clone)]
+    struct Node {
+        chain_id: &'static str,
+    }
+
+    impl Transport for Node {
+        type Out = Ready<web3::error::Result<Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+            (1, web3::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, request: Call) -> Self::Out {
+            let Call::MethodCall(call) = request else {
+                unreachable!();
+            };
+            ready(Ok(match call.method.as_str() {
+                "eth_chainId" => json!(self.chain_id),
+                _ => json!({
+                    "number": "0x0",
+                    "hash": format!("0x{:064x}", 0xd4e5),
+                    "parentHash": format!("0x{:064x}", 0),
+                    "sha3Uncles": format!("0x{:064x}", 0),
+                    "miner": format!("0x{:040x}", 0),
+                    "stateRoot": format!("0x{:064x}", 0),
+                    "transactionsRoot": format!("0x{:064x}", 0),
+                    "receiptsRoot": format!("0x{:064x}", 0),
+                    "gasUsed": "0x0",
+                    "gasLimit": "0x1388",
+                    "extraData": "0x",
+                    "timestamp": "0x0",
+                    "difficulty": "0x400000000",
+                    "size": "0x21c",
+                    "uncles": [],
+                    "transactions": [],
+                }),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_the_chain_id_and_genesis_hash() {
+        let identity = fetch_identity(&Web3::new(Node { chain_id: "0xaa36a7" })).await.unwrap();
+        assert_eq!(identity, ChainIdentity { chain_id: 11155111, genesis_hash: format!("0x{:064x}", 0xd4e5) });
+    }
+
+    #[tokio::test]
+    async fn rejects_chain_ids_beyond_64_bits() {
+        let error = fetch_identity(&Web3::new(Node { chain_id: "0x10000000000000000" })).await.unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(IndexerError::RpcError(_))), "{}", error);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config::HedgeConfig;
+use futures::future::{self, BoxFuture, Either};
+use jsonrpc_core::{Call, Value};
+use metrics::{counter, gauge};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use web3::{RequestId, Transport};
+
+/// Number of recent primary latencies used to derive the hedge delay.
+const LATENCY_WINDOW: usize = 256;
+/// Below this many samples the configured maximum delay is used.
+const MIN_LATENCY_SAMPLES: usize = 20;
+/// Methods only ever sent to the primary. Endpoints can be at different
+/// heights, and a head poll answered by a lagging secondary would make the
+/// chain head appear to move backwards.
+const UNHEDGED_METHODS: &[&str] = &["eth_blockNumber"];
+
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    samples: VecDeque<Duration>,
+}
+
+impl LatencyWindow {
+    fn record(&mut self, latency: Duration) {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.samples.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = ((sorted.len() - 1) as f64 * percentile.clamp(0.0, 1.0)).round() as usize;
+        Some(sorted[rank])
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint<T> {
+    label: String,
+    transport: T,
+}
+
+/// Transport that sends every request to the primary endpoint and, if no
+/// answer arrives within a latency-percentile delay, issues the same request
+/// to a secondary endpoint and takes whichever succeeds first. A primary that
+/// fails before the delay is retried on the secondary at once. Head polls are
+/// not hedged.
+#[derive(Debug, Clone)]
+pub struct HedgedTransport<T> {
+    endpoints: Arc<Vec<Endpoint<T>>>,
+    config: HedgeConfig,
+    latencies: Arc<Mutex<LatencyWindow>>,
+    next_secondary: Arc<AtomicUsize>,
+}
+
+impl<T> HedgedTransport<T> {
+    /// `endpoints` holds `(label, transport)` pairs, primary first.
+    pub fn new(endpoints: Vec<(String, T)>, config: &HedgeConfig) -> Self {
+        Self {
+            endpoints: Arc::new(
+                endpoints.into_iter()
+                    .map(|(label, transport)| Endpoint { label, transport })
+                    .collect(),
+            ),
+            config: config.clone(),
+            latencies: Arc::new(Mutex::new(LatencyWindow::default())),
+            next_secondary: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// `(label, transport)` of every endpoint, primary first.
+    pub fn endpoints(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.endpoints.iter().map(|endpoint| (endpoint.label.as_str(), &endpoint.transport))
+    }
+
+    fn hedge_delay(&self) -> Duration {
+        let min = Duration::from_millis(self.config.min_delay_ms);
+        let max = Duration::from_millis(self.config.max_delay_ms).max(min);
+        self.latencies.lock().unwrap()
+            .percentile(self.config.percentile)
+            .unwrap_or(max)
+            .clamp(min, max)
+    }
+}
+
+impl<T> Transport for HedgedTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, web3::error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.endpoints[0].transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let primary = &self.endpoints[0];
+
+        let unhedged = match &request {
+            Call::MethodCall(call) => UNHEDGED_METHODS.contains(&call.method.as_str()),
+            _ => true,
+        };
+        if !self.config.enabled || self.endpoints.len() < 2 || unhedged {
+            return Box::pin(primary.transport.send(id, request));
+        }
+
+        let delay = self.hedge_delay();
+        gauge!("rpc_hedge_delay_seconds").set(delay.as_secs_f64());
+
+        let secondary_index = 1 + self.next_secondary.fetch_add(1, Ordering::Relaxed) % (self.endpoints.len() - 1);
+        let secondary = &self.endpoints[secondary_index];
+        let secondary_label = secondary.label.clone();
+        let secondary_out = Box::pin(secondary.transport.send(id, request.clone()));
+
+        let latencies = self.latencies.clone();
+        let started = Instant::now();
+        let primary_out = primary.transport.send(id, request);
+
+        Box::pin(async move {
+            let primary_latencies = latencies.clone();
+            let primary_out = async move {
+                let result = primary_out.await;
+                primary_latencies.lock().unwrap().record(started.elapsed());
+                result
+            };
+            futures::pin_mut!(primary_out);
+
+            let primary_out = match future::select(primary_out, Box::pin(tokio::time::sleep(delay))).await {
+                Either::Left((Ok(value), _)) => return Ok(value),
+                Either::Left((Err(_), _)) => {
+                    counter!("rpc_hedged_requests_total", "endpoint" => secondary_label).increment(1);
+                    let result = secondary_out.await;
+                    let winner = if result.is_ok() { "hedge" } else { "none" };
+                    counter!("rpc_hedge_outcomes_total", "winner" => winner).increment(1);
+                    return result;
+                }
+                Either::Right((_, primary_out)) => primary_out,
+            };
+
+            counter!("rpc_hedged_requests_total", "endpoint" => secondary_label.clone()).increment(1);
+
+            match future::select(primary_out, secondary_out).await {
+                Either::Left((Ok(value), _)) => {
+                    counter!("rpc_hedge_outcomes_total", "winner" => "primary").increment(1);
+                    Ok(value)
+                }
+                Either::Right((Ok(value), _)) => {
+                    // The primary was at least this slow; recording it keeps
+                    // the percentile from drifting down while hedges win.
+                    latencies.lock().unwrap().record(started.elapsed());
+                    counter!("rpc_hedge_outcomes_total", "winner" => "hedge").increment(1);
+                    counter!("rpc_hedge_wins_total", "endpoint" => secondary_label).increment(1);
+                    Ok(value)
+                }
+                Either::Left((Err(_), secondary_out)) => {
+                    let result = secondary_out.await;
+                    let winner = if result.is_ok() { "hedge" } else { "none" };
+                    counter!("rpc_hedge_outcomes_total", "winner" => winner).increment(1);
+                    result
+                }
+                Either::Right((Err(_), primary_out)) => {
+                    let result = primary_out.await;
+                    let winner = if result.is_ok() { "primary" } else { "none" };
+                    counter!("rpc_hedge_outcomes_total", "winner" => winner).increment(1);
+                    result
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Answers every request with `answer` after `delay`, or fails.
+    #[derive(Debug, Clone)]
+    struct Node {
+        delay: Duration,
+        answer: Option<&'static str>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Node {
+        fn new(delay_ms: u64, answer: Option<&'static str>) -> Self {
+            Self { delay: Duration::from_millis(delay_ms), answer, calls: Arc::new(AtomicUsize::new(0)) }
+        }
+    }
+
+    impl Transport for Node {
+        type Out = BoxFuture<'static, web3::error::Result<Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+            (1, web3::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: Call) -> Self::Out {
+            let node = self.clone();
+            Box::pin(async move {
+                node.calls.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(node.delay).await;
+                node.answer.map(|answer| json!(answer)).ok_or(web3::Error::Unreachable)
+            })
+        }
+    }
+
+    fn hedged(primary: &Node, secondary: &Node, delay_ms: u64) -> HedgedTransport<Node> {
+        let config = HedgeConfig { enabled: true, percentile: 0.95, min_delay_ms: delay_ms, max_delay_ms: delay_ms };
+        HedgedTransport::new(vec![
+            ("primary".to_string(), primary.clone()),
+            ("secondary".to_string(), secondary.clone()),
+        ], &config)
+    }
+
+    fn window(millis: impl IntoIterator<Item = u64>) -> LatencyWindow {
+        let mut window = LatencyWindow::default();
+        for ms in millis {
+            window.record(Duration::from_millis(ms));
+        }
+        window
+    }
+
+    fn transport(min_delay_ms: u64, max_delay_ms: u64, samples: LatencyWindow) -> HedgedTransport<()> {
+        let config = HedgeConfig { enabled: true, percentile: 0.95, min_delay_ms, max_delay_ms };
+        let transport = HedgedTransport::new(Vec::new(), &config);
+        *transport.latencies.lock().unwrap() = samples;
+        transport
+    }
+
+    #[test]
+    fn percentile_needs_enough_samples() {
+        assert_eq!(window(1..MIN_LATENCY_SAMPLES as u64).percentile(0.5), None);
+        assert!(window(1..=MIN_LATENCY_SAMPLES as u64).percentile(0.5).is_some());
+    }
+
+    #[test]
+    fn percentile_rounds_to_the_nearest_rank() {
+        // 1..=21 ms in shuffled order: rank = round(20 * p).
+        let samples = window((1..=21).rev());
+        assert_eq!(samples.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(samples.percentile(0.5), Some(Duration::from_millis(11)));
+        assert_eq!(samples.percentile(0.95), Some(Duration::from_millis(20)));
+        assert_eq!(samples.percentile(0.99), Some(Duration::from_millis(21)));
+        assert_eq!(samples.percentile(2.0), Some(Duration::from_millis(21)));
+    }
+
+    #[test]
+    fn window_keeps_only_recent_samples() {
+        // The first 100 samples of 1000 ms fall out of the window.
+        let samples = window(std::iter::repeat_n(1000, 100).chain(std::iter::repeat_n(5, LATENCY_WINDOW)));
+        assert_eq!(samples.samples.len(), LATENCY_WINDOW);
+        assert_eq!(samples.percentile(1.0), Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn hedge_delay_is_clamped_to_configured_bounds() {
+        assert_eq!(transport(50, 500, window(1..10)).hedge_delay(), Duration::from_millis(500));
+        assert_eq!(transport(50, 500, window(std::iter::repeat_n(5, 30))).hedge_delay(), Duration::from_millis(50));
+        assert_eq!(transport(50, 500, window(std::iter::repeat_n(100, 30))).hedge_delay(), Duration::from_millis(100));
+        assert_eq!(transport(50, 500, window(std::iter::repeat_n(2000, 30))).hedge_delay(), Duration::from_millis(500));
+        // A maximum below the minimum is raised to it.
+        assert_eq!(transport(50, 10, window(1..10)).hedge_delay(), Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn takes_the_secondary_answer_when_the_primary_is_slow() {
+        let (primary, secondary) = (Node::new(2000, Some("primary")), Node::new(0, Some("secondary")));
+        let started = Instant::now();
+        let answer = hedged(&primary, &secondary, 20).execute("eth_getBlockByNumber", Vec::new()).await.unwrap();
+        assert_eq!(answer, json!("secondary"));
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn sends_no_hedge_when_the_primary_answers_in_time() {
+        let (primary, secondary) = (Node::new(0, Some("primary")), Node::new(0, Some("secondary")));
+        let answer = hedged(&primary, &secondary, 500).execute("eth_getBlockByNumber", Vec::new()).await.unwrap();
+        assert_eq!(answer, json!("primary"));
+        assert_eq!(secondary.calls.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn retries_a_failed_primary_on_the_secondary_at_once() {
+        let (primary, secondary) = (Node::new(0, None), Node::new(0, Some("secondary")));
+        let started = Instant::now();
+        let answer = hedged(&primary, &secondary, 5000).execute("eth_getBlockByNumber", Vec::new()).await.unwrap();
+        assert_eq!(answer, json!("secondary"));
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn never_hedges_head_polls() {
+        // The secondary lags behind; its answer must not be used.
+        let (primary, secondary) = (Node::new(100, Some("0x20")), Node::new(0, Some("0x10")));
+        let answer = hedged(&primary, &secondary, 10).execute("eth_blockNumber", Vec::new()).await.unwrap();
+        assert_eq!(answer, json!("0x20"));
+        assert_eq!(secondary.calls.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,72 @@
+mod cassette;
+mod chain;
+mod hedge;
+mod rate_limit;
+
+use crate::config::{Config, RpcMode};
+use crate::core::Shutdown;
+use anyhow::Result;
+use std::sync::Arc;
+use web3::{transports::Http, Web3};
+
+pub use cassette::{Cassette, CassetteTransport, Recorder};
+pub use chain::verify_chain;
+pub use hedge::HedgedTransport;
+pub use rate_limit::{as_backfill, RateLimitedTransport, RateLimiter};
+
+/// Transport stack used for all JSON-RPC traffic.
+pub type RpcTransport = CassetteTransport<HedgedTransport<RateLimitedTransport<Http>>>;
+
+/// Builds the client for the configured endpoints, primary first, each
+/// with its own rate limiter. In replay mode no endpoint is contacted.
+pub fn connect(config: &Config, shutdown: &Shutdown) -> Result<Web3<RpcTransport>> {
+    if config.rpc_mode == RpcMode::Replay {
+        let cassette = Cassette::load(&config.cassette_path)?;
+        return Ok(Web3::new(CassetteTransport::Replay(Arc::new(cassette))));
+    }
+
+    let endpoints = std::iter::once(&config.rpc_endpoint)
+        .chain(&config.secondary_rpc_endpoints)
+        .map(|url| {
+            let label = endpoint_label(url);
+            let limiter = Arc::new(RateLimiter::new(&config.rate_limit, &label, &config.data_dir, shutdown.clone())?);
+            Ok((label, RateLimitedTransport::new(Http::new(url)?, limiter)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let transport = HedgedTransport::new(endpoints, &config.hedge);
+
+    Ok(Web3::new(match config.rpc_mode {
+        RpcMode::Record => CassetteTransport::Record(transport, Arc::new(Recorder::open(&config.cassette_path)?)),
+        _ => CassetteTransport::Live(transport),
+    }))
+}
+
+/// Rate limiter for another connection to `url`, such as a WebSocket. An
+/// HTTP endpoint on the same host lends its own, so both draw on one budget.
+pub fn limiter_for(
+    config: &Config,
+    client: &Web3<RpcTransport>,
+    url: &str,
+    shutdown: &Shutdown,
+) -> Result<Arc<RateLimiter>> {
+    let label = endpoint_label(url);
+    let shared = client.transport().upstream().and_then(|hedged| {
+        hedged.endpoints()
+            .find(|(endpoint, _)| *endpoint == label)
+            .map(|(_, transport)| transport.limiter().clone())
+    });
+    match shared {
+        Some(limiter) => Ok(limiter),
+        None => Ok(Arc::new(RateLimiter::new(&config.rate_limit, &label, &config.data_dir, shutdown.clone())?)),
+    }
+}
+
+/// Short name for an endpoint, used in metric labels and file names. Only the
+/// host is kept so API keys embedded in the URL path are never exposed.
+pub fn endpoint_label(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme.split('/').next().unwrap_or(without_scheme);
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    host.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "_")
+}
@@ -0,0 +1,340 @@
+use crate::config::RateLimitConfig;
+use crate::core::Shutdown;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use futures::future::BoxFuture;
+use jsonrpc_core::{Call, Value};
+use metrics::{counter, gauge, histogram};
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+use web3::{error::TransportError, RequestId, Transport};
+
+/// How often budget usage is written back to disk.
+const BUDGET_PERSIST_INTERVAL: Duration = Duration::from_secs(10);
+
+tokio::task_local! {
+    /// Set while a backfill task runs, so its requests give way to the head.
+    static BACKFILL: ();
+}
+
+/// Runs `future` as backfill work: its requests pause once the budget left
+/// outside the head reserve is spent.
+pub async fn as_backfill<F: Future>(future: F) -> F::Output {
+    BACKFILL.scope((), future).await
+}
+
+fn is_backfill() -> bool {
+    BACKFILL.try_with(|_| ()).is_ok()
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        // One second worth of burst, but never less than a single request.
+        let capacity = rate.max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes `cost` tokens and returns how long the caller has to wait before
+    /// the tokens it borrowed are refilled.
+    fn reserve(&mut self, cost: f64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+        self.tokens -= cost;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct BudgetUsage {
+    day: String,
+    day_used: u64,
+    month: String,
+    month_used: u64,
+}
+
+#[derive(Debug)]
+struct Budget {
+    path: PathBuf,
+    usage: BudgetUsage,
+    last_persist: Instant,
+}
+
+/// Per-endpoint request limiter: a token bucket for requests per second, a
+/// second one for weighted compute units per second, and daily/monthly
+/// compute-unit budgets that are persisted in `data_dir`. Backfill requests
+/// stop short of the budgets' head reserve; head requests may use all of it.
+#[derive(Debug)]
+pub struct RateLimiter {
+    endpoint: String,
+    config: RateLimitConfig,
+    requests: Option<Mutex<TokenBucket>>,
+    compute_units: Option<Mutex<TokenBucket>>,
+    budget: Mutex<Budget>,
+    shutdown: Shutdown,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig, endpoint: &str, data_dir: &Path, shutdown: Shutdown) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(format!("rpc_budget_{}.json", endpoint));
+        let usage = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BudgetUsage::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            endpoint: endpoint.to_string(),
+            config: config.clone(),
+            requests: config.requests_per_second.map(|rate| Mutex::new(TokenBucket::new(rate))),
+            compute_units: config.compute_units_per_second.map(|rate| Mutex::new(TokenBucket::new(rate))),
+            budget: Mutex::new(Budget {
+                path,
+                usage,
+                last_persist: Instant::now(),
+            }),
+            shutdown,
+        })
+    }
+
+    fn method_cost(&self, method: &str) -> u64 {
+        self.config.method_compute_units
+            .get(method)
+            .copied()
+            .unwrap_or(self.config.default_compute_units)
+    }
+
+    /// Waits until a request for `method` fits both the rate limits and the
+    /// compute-unit budget, then charges it. Fails if shutdown is requested
+    /// while the budget is exhausted.
+    pub async fn acquire(&self, method: &str, backfill: bool) -> web3::error::Result<()> {
+        let cost = self.method_cost(method);
+        let priority = if backfill { "backfill" } else { "head" };
+
+        // When the budget is spent we pause until the window resets rather
+        // than exceeding the provider quota.
+        while let Some(reset_at) = self.charge_budget(cost, backfill) {
+            let wait = (reset_at - Utc::now()).to_std().unwrap_or(Duration::from_secs(1));
+            warn!(
+                event = "rpc_budget_exhausted",
+                message = "Compute unit budget exhausted, pausing requests",
+                endpoint = %self.endpoint,
+                priority = priority,
+                resume_at = %reset_at,
+            );
+            gauge!("rpc_budget_exhausted", "endpoint" => self.endpoint.clone(), "priority" => priority).set(1.0);
+            if self.shutdown.sleep(wait).await {
+                return Err(web3::Error::Transport(TransportError::Message(
+                    "Shutdown requested while the compute unit budget is exhausted".into(),
+                )));
+            }
+        }
+        gauge!("rpc_budget_exhausted", "endpoint" => self.endpoint.clone(), "priority" => priority).set(0.0);
+
+        let mut wait = Duration::ZERO;
+        if let Some(bucket) = &self.requests {
+            wait = wait.max(bucket.lock().unwrap().reserve(1.0));
+        }
+        if let Some(bucket) = &self.compute_units {
+            wait = wait.max(bucket.lock().unwrap().reserve(cost as f64));
+        }
+
+        counter!("rpc_compute_units_total", "endpoint" => self.endpoint.clone(), "method" => method.to_string())
+            .increment(cost);
+
+        if !wait.is_zero() {
+            counter!("rpc_throttled_requests_total", "endpoint" => self.endpoint.clone()).increment(1);
+            histogram!("rpc_throttle_wait_seconds", "endpoint" => self.endpoint.clone())
+                .record(wait.as_secs_f64());
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// Adds `cost` to the current day and month, or returns when the budget
+    /// that would be exceeded resets. Backfill requests only get the part of
+    /// each budget outside the head reserve.
+    fn charge_budget(&self, cost: u64, backfill: bool) -> Option<DateTime<Utc>> {
+        let limit = |limit: u64| if backfill { self.config.backfill_share(limit) } else { limit };
+        let now = Utc::now();
+        let day = now.format("%Y-%m-%d").to_string();
+        let month = now.format("%Y-%m").to_string();
+
+        let mut budget = self.budget.lock().unwrap();
+        let usage = &mut budget.usage;
+        if usage.day != day {
+            usage.day = day;
+            usage.day_used = 0;
+        }
+        if usage.month != month {
+            usage.month = month;
+            usage.month_used = 0;
+        }
+
+        if let Some(limit) = self.config.monthly_compute_units.map(limit) {
+            if usage.month_used + cost > limit {
+                return Some(next_month(now));
+            }
+        }
+        if let Some(limit) = self.config.daily_compute_units.map(limit) {
+            if usage.day_used + cost > limit {
+                return Some(next_day(now));
+            }
+        }
+
+        usage.day_used += cost;
+        usage.month_used += cost;
+        gauge!("rpc_budget_daily_used", "endpoint" => self.endpoint.clone()).set(usage.day_used as f64);
+        gauge!("rpc_budget_monthly_used", "endpoint" => self.endpoint.clone()).set(usage.month_used as f64);
+
+        if budget.last_persist.elapsed() >= BUDGET_PERSIST_INTERVAL {
+            budget.persist();
+        }
+
+        None
+    }
+}
+
+impl Budget {
+    fn persist(&mut self) {
+        self.last_persist = Instant::now();
+        if let Err(e) = serde_json::to_vec(&self.usage)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(std::fs::write(&self.path, bytes)?))
+        {
+            error!(
+                event = "rpc_budget_persist_error",
+                message = "Failed to persist compute unit budget",
+                error = %e
+            );
+        }
+    }
+}
+
+impl Drop for RateLimiter {
+    /// Saves usage charged since the last periodic write.
+    fn drop(&mut self) {
+        if let Ok(budget) = self.budget.get_mut() {
+            budget.persist();
+        }
+    }
+}
+
+fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive().succ_opt().unwrap_or(now.date_naive());
+    Utc.from_utc_datetime(&tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default())
+}
+
+fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(now.date_naive());
+    Utc.from_utc_datetime(&first.and_hms_opt(0, 0, 0).unwrap_or_default())
+}
+
+/// Transport that passes every request through a shared `RateLimiter`.
+#[derive(Debug, Clone)]
+pub struct RateLimitedTransport<T> {
+    inner: T,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<T> RateLimitedTransport<T> {
+    pub fn new(inner: T, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+}
+
+impl<T> Transport for RateLimitedTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, web3::error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let method = match &request {
+            Call::MethodCall(call) => call.method.clone(),
+            Call::Notification(notification) => notification.method.clone(),
+            Call::Invalid { .. } => String::new(),
+        };
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        let backfill = is_backfill();
+
+        Box::pin(async move {
+            limiter.acquire(&method, backfill).await?;
+            inner.send(id, request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn backfills_leave_the_head_reserve() {
+        let config = RateLimitConfig {
+            requests_per_second: None,
+            compute_units_per_second: None,
+            method_compute_units: Default::default(),
+            default_compute_units: 10,
+            daily_compute_units: Some(100),
+            monthly_compute_units: None,
+            head_reserve_percent: 20,
+        };
+        let dir = std::env::temp_dir().join(format!("rate_limit_test_{}", std::process::id()));
+        let limiter = RateLimiter::new(&config, "test", &dir, Shutdown::new()).unwrap();
+
+        for _ in 0..8 {
+            assert!(limiter.charge_budget(10, true).is_none());
+        }
+        assert!(limiter.charge_budget(10, true).is_some());
+        assert!(limiter.charge_budget(10, false).is_none());
+        assert!(limiter.charge_budget(10, false).is_none());
+        assert!(limiter.charge_budget(10, false).is_some());
+
+        drop(limiter);
+        let saved: BudgetUsage = serde_json::from_slice(&std::fs::read(dir.join("rpc_budget_test.json")).unwrap()).unwrap();
+        assert_eq!(saved.day_used, 100);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
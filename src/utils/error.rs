@@ -1,6 +1,7 @@
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum IndexerError {
     #[error("RPC error: {0}")]
     RpcError(String),
@@ -10,4 +11,45 @@ pub enum IndexerError {
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
-}
\ No newline at end of file
+
+    #[error("Chain mismatch: {0}")]
+    ChainMismatch(String),
+
+    #[error("Source error: {0}")]
+    SourceError(String),
+
+    #[error("Decode error: {0}")]
+    DecodeError(#[from] DecodeError),
+
+    /// A block that could not be decoded, kept with its raw JSON so it can
+    /// be written to the dead-letter file.
+    #[error("Malformed block {number}: {error}")]
+    MalformedBlock {
+        number: u64,
+        error: DecodeError,
+        raw: Box<serde_json::Value>,
+    },
+}
+
+/// Why a block returned by a node or read from a dump could not be converted
+/// into our model.
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("block is pending, {0} is missing")]
+    Pending(&'static str),
+
+    #[error("missing field {0}")]
+    MissingField(&'static str),
+
+    #[error("field {0} overflows u64")]
+    Overflow(&'static str),
+
+    #[error("invalid field {field}: {reason}")]
+    InvalidField {
+        field: &'static str,
+        reason: String,
+    },
+
+    #[error("malformed block: {0}")]
+    Malformed(String),
+}
@@ -0,0 +1,93 @@
+//! Replays a recorded cassette through the indexer binary and checks the
+//! parquet files it publishes.
+//!
+//! `fixtures/blocks_10_17.jsonl.gz` holds the RPC exchanges for blocks 10..=17
+//! of a small chain with two EIP-7702 authorizations, so the run needs no node.
+
+use arrow::array::{Array, StringArray, UInt64Array};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn wait_for(mut done: impl FnMut() -> bool) {
+    let started = Instant::now();
+    while !done() {
+        assert!(started.elapsed() < TIMEOUT, "indexer did not get there within {:?}", TIMEOUT);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn start_indexer(data_dir: &Path, log: &Path) -> Child {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/blocks_10_17.jsonl.gz");
+    Command::new(env!("CARGO_BIN_EXE_eth-high-perf-indexer"))
+        .env("RPC_MODE", "replay")
+        .env("RPC_CASSETTE", fixture)
+        .env("DATA_DIR", data_dir)
+        .env("METRICS_PORT", (20000 + std::process::id() % 10000).to_string())
+        .env("START_BLOCK", "10")
+        .stdout(File::create(log).unwrap())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+fn stop(child: &mut Child) {
+    let status = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    let mut exit = None;
+    wait_for(|| {
+        exit = child.try_wait().unwrap();
+        exit.is_some()
+    });
+    assert!(exit.unwrap().success(), "indexer exited with {:?}", exit);
+}
+
+fn published(data_dir: &Path, dataset: &str) -> PathBuf {
+    data_dir.join(format!("{}_000000000_000009999.parquet", dataset))
+}
+
+fn column<T: Array + Clone + 'static>(path: &Path, name: &str) -> T {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+    let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+    assert_eq!(batches.len(), 1);
+    batches[0].column_by_name(name).unwrap().as_any().downcast_ref::<T>().unwrap().clone()
+}
+
+#[test]
+fn replays_cassette_into_parquet() {
+    let data_dir = std::env::temp_dir().join(format!("indexer_replay_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_dir);
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let log = data_dir.join("indexer.log");
+
+    let mut child = start_indexer(&data_dir, &log);
+    wait_for(|| {
+        std::fs::read_to_string(&log).unwrap_or_default()
+            .lines()
+            .any(|line| line.contains("\"event\":\"block_processed\"") && line.contains("\"block_number\":17"))
+    });
+    stop(&mut child);
+
+    let blocks = published(&data_dir, "blocks");
+    let numbers: UInt64Array = column(&blocks, "number");
+    assert_eq!(numbers.values().to_vec(), (10..=17).collect::<Vec<u64>>());
+    let hashes: StringArray = column(&blocks, "hash");
+    let parents: StringArray = column(&blocks, "parent_hash");
+    for row in 1..numbers.len() {
+        assert_eq!(parents.value(row), hashes.value(row - 1));
+    }
+
+    let authorizations = published(&data_dir, "authorizations");
+    let authorized_blocks: UInt64Array = column(&authorizations, "block_number");
+    assert_eq!(authorized_blocks.values().to_vec(), vec![12, 14]);
+    let chain_ids: StringArray = column(&authorizations, "chain_id");
+    assert!(chain_ids.iter().all(|chain_id| chain_id == Some("1")));
+
+    std::fs::remove_dir_all(&data_dir).unwrap();
+}